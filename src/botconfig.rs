@@ -2,6 +2,80 @@ use config::{Config, Environment, File};
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
 
+/// A kind of ticket users can open, e.g. "report" or "appeal", each handled by its own staff.
+#[derive(Debug, Deserialize)]
+pub struct TicketTopic {
+    pub(crate) name: String,
+    pub(crate) label: String,
+    pub(crate) category: u64,
+    pub(crate) staff_role: u64,
+    pub(crate) channel_prefix: String,
+    pub(crate) welcome_text: String,
+}
+
+/// A question asked in the form shown when opening a ticket.
+#[derive(Debug, Deserialize)]
+pub struct IntakeField {
+    pub(crate) label: String,
+    /// Whether the answer is a multi-line paragraph rather than a single line
+    #[serde(default)]
+    pub(crate) paragraph: bool,
+    #[serde(default)]
+    pub(crate) required: bool,
+    pub(crate) placeholder: Option<String>,
+}
+
+/// How ticket conversations are hosted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketMode {
+    /// A text channel per ticket in the topic's category
+    #[default]
+    Channel,
+    /// A private thread per ticket in `ticket_thread_channel`
+    Thread,
+}
+
+/// The embed posted by `/ticketpanel`.
+#[derive(Debug, Deserialize)]
+pub struct PanelSettings {
+    pub(crate) title: String,
+    pub(crate) text: String,
+}
+
+impl Default for PanelSettings {
+    fn default() -> Self {
+        PanelSettings {
+            title: "Shalom Support".to_string(),
+            text:
+                "Need help? Press a button below to open a ticket with the Shalom Support Team ™."
+                    .to_string(),
+        }
+    }
+}
+
+/// A field of the embed posted when a ticket is opened.
+#[derive(Debug, Deserialize)]
+pub struct WelcomeField {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    #[serde(default)]
+    pub(crate) inline: bool,
+}
+
+/// The embed posted when a ticket is opened. Every text may use the placeholders `{opener}`,
+/// `{ticket_id}`, `{topic}`, `{opened_at}` and `{opened_at_relative}`.
+#[derive(Debug, Deserialize)]
+pub struct WelcomeSettings {
+    pub(crate) title: String,
+    pub(crate) description: String,
+    pub(crate) thumbnail: Option<String>,
+    /// Defaults to `embed_color`
+    pub(crate) color: Option<i32>,
+    #[serde(default)]
+    pub(crate) fields: Vec<WelcomeField>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GuildSettings {
     pub(crate) guild_id: u64,
@@ -10,6 +84,71 @@ pub struct GuildSettings {
     pub(crate) crossword_channel: u64,
     pub(crate) everyone_role: u64,
     pub(crate) ticket_log_channel: u64,
+    /// Maximum size in bytes of a file uploaded to the ticket log channel
+    pub(crate) log_upload_limit: u64,
+    /// Ticket topics to choose from when opening a ticket. If none are configured, a single
+    /// "general" topic using `ticket_category` and `support_team_role` is used.
+    #[serde(default)]
+    pub(crate) ticket_topics: Vec<TicketTopic>,
+    #[serde(default)]
+    pub(crate) ticket_mode: TicketMode,
+    /// Channel private ticket threads are created in when `ticket_mode` is `thread`
+    pub(crate) ticket_thread_channel: Option<u64>,
+    /// Whether direct messages to the bot open modmail tickets and are relayed into them
+    #[serde(default)]
+    pub(crate) modmail: bool,
+    /// Maximum number of tickets a user may have open at once, or 0 for no limit
+    pub(crate) max_open_tickets: usize,
+    /// Minimum number of seconds between a user opening two tickets
+    pub(crate) ticket_cooldown: i64,
+    /// Seconds without activity after which a ticket is warned it will be closed, or 0 to never
+    /// close tickets for inactivity
+    #[serde(default)]
+    pub(crate) inactivity_warning: i64,
+    /// Seconds without activity after which a warned ticket is closed
+    #[serde(default)]
+    pub(crate) inactivity_close: i64,
+    /// Channel in which staff are alerted about tickets waiting too long for a reply, or 0 to
+    /// disable alerts
+    #[serde(default)]
+    pub(crate) sla_alert_channel: u64,
+    /// Role pinged by alerts instead of the ticket topic's staff role
+    pub(crate) sla_escalation_role: Option<u64>,
+    /// Seconds a new ticket may wait for its first staff reply
+    #[serde(default)]
+    pub(crate) sla_first_response: i64,
+    /// Seconds a ticket author may wait for a staff reply after writing again
+    #[serde(default)]
+    pub(crate) sla_follow_up: i64,
+    /// Category closed ticket channels are moved to instead of being deleted, so they can be
+    /// reopened. Closed ticket threads are instead archived and locked in place. If not set, closed
+    /// tickets are deleted immediately.
+    pub(crate) archive_category: Option<u64>,
+    /// Seconds after closing during which an archived ticket can be reopened
+    #[serde(default)]
+    pub(crate) reopen_window: i64,
+    /// Seconds after closing after which an archived ticket channel is deleted
+    #[serde(default)]
+    pub(crate) archive_retention: i64,
+    #[serde(default)]
+    pub(crate) panel: PanelSettings,
+    pub(crate) welcome: WelcomeSettings,
+    /// Questions asked when opening a ticket, at most 5. Defaults to a subject and description.
+    #[serde(default)]
+    pub(crate) intake_fields: Vec<IntakeField>,
+}
+
+impl GuildSettings {
+    pub fn topic(&self, name: &str) -> Option<&TicketTopic> {
+        self.ticket_topics.iter().find(|topic| topic.name == name)
+    }
+
+    /// The topic of tickets whose topic is no longer configured.
+    pub fn default_topic(&self) -> &TicketTopic {
+        self.ticket_topics
+            .first()
+            .expect("there is always at least one ticket topic")
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,6 +156,7 @@ pub struct BotConfig {
     pub(crate) discord_token: String,
     pub(crate) guild_settings: GuildSettings,
     pub(crate) embed_color: i32,
+    pub(crate) database_path: String,
 }
 
 impl BotConfig {
@@ -30,7 +170,34 @@ impl BotConfig {
                 .build()
                 .unwrap();
             // You can deserialize (and thus freeze) the entire configuration as
-            s.try_deserialize().unwrap()
+            let mut cfg: BotConfig = s.try_deserialize().unwrap();
+            if cfg.guild_settings.ticket_topics.is_empty() {
+                cfg.guild_settings.ticket_topics.push(TicketTopic {
+                    name: "general".to_string(),
+                    label: "General Support".to_string(),
+                    category: cfg.guild_settings.ticket_category,
+                    staff_role: cfg.guild_settings.support_team_role,
+                    channel_prefix: "ticket".to_string(),
+                    welcome_text: "A Shalom Supporter ™ will be with you shortly.".to_string(),
+                });
+            }
+            if cfg.guild_settings.intake_fields.is_empty() {
+                cfg.guild_settings.intake_fields = vec![
+                    IntakeField {
+                        label: "Subject".to_string(),
+                        paragraph: false,
+                        required: true,
+                        placeholder: None,
+                    },
+                    IntakeField {
+                        label: "Description".to_string(),
+                        paragraph: true,
+                        required: true,
+                        placeholder: None,
+                    },
+                ];
+            }
+            cfg
         })
     }
 }
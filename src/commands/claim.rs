@@ -0,0 +1,98 @@
+use log::error;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, PermissionOverwrite,
+    PermissionOverwriteType, Permissions,
+};
+use serenity::client::Context;
+use serenity::prelude::Mentionable;
+
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    if let Some(claimer_id) = ticket.claimer_id {
+        return format!(
+            "This ticket is already claimed by {}. Use /unclaim first to reassign it.",
+            claimer_id.mention()
+        );
+    }
+
+    let exclusive = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "exclusive")
+        .and_then(|option| option.value.as_bool())
+        .unwrap_or(false);
+
+    if exclusive && ticket.thread {
+        return "Exclusive claims are not possible for thread tickets.".to_string();
+    }
+
+    if exclusive {
+        let mut overwrites: Vec<PermissionOverwrite> =
+            tickets::staff_roles(ticket.topic_settings())
+                .into_iter()
+                .map(|role| PermissionOverwrite {
+                    allow: Permissions::VIEW_CHANNEL,
+                    deny: Permissions::SEND_MESSAGES,
+                    kind: PermissionOverwriteType::Role(role),
+                })
+                .collect();
+        overwrites.push(PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(command.user.id),
+        });
+        for overwrite in overwrites {
+            if let Err(why) = command
+                .channel_id
+                .create_permission(&ctx.http, overwrite)
+                .await
+            {
+                error!("Error restricting ticket to claimer: {}", why);
+                return "Error restricting ticket permissions".to_string();
+            }
+        }
+    }
+
+    let ticket = match store.set_claimer(ticket.id, Some(command.user.id), command.user.id) {
+        Ok(ticket) => ticket,
+        Err(why) => {
+            error!("Error recording ticket claim: {}", why);
+            return "Error claiming ticket".to_string();
+        }
+    };
+
+    if let Err(why) = tickets::refresh_welcome_embed(ctx, command.channel_id, &ticket).await {
+        error!("Error updating ticket welcome message: {}", why);
+    }
+
+    if exclusive {
+        format!(
+            "{} has claimed this ticket and is now the only supporter who can reply.",
+            command.user.mention()
+        )
+    } else {
+        format!("{} has claimed this ticket.", command.user.mention())
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("claim")
+        .description("Take ownership of a shalom ticket")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "exclusive",
+                "only allow you to reply in this ticket",
+            )
+            .required(false),
+        )
+}
@@ -1,35 +1,204 @@
-use crate::botconfig::BotConfig;
-use serenity::all::{CommandInteraction, CreateCommand, CreateMessage, ReactionType, RoleId};
+use crate::tickets;
+use crate::tickets::store::{EventKind, Ticket, TicketStore};
+use log::error;
+use serenity::all::{
+    ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction, CreateActionRow,
+    CreateButton, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, RoleId,
+};
 use serenity::client::Context;
+use serenity::prelude::Mentionable;
+
+pub const CONFIRM_BUTTON: &str = "ticket_close_confirm";
+pub const CANCEL_BUTTON: &str = "ticket_close_cancel";
 
 pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
-    if !command.member.as_ref().is_some_and(|m| {
-        m.roles.contains(&RoleId::new(
-            BotConfig::global_cfg().guild_settings.support_team_role,
-        ))
-    }) {
+    let ticket = match TicketStore::global().open_ticket_by_channel(command.channel_id) {
+        Ok(ticket) => ticket,
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            None
+        }
+    };
+
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| is_staff(&m.roles, ticket.as_ref()))
+    {
         return "This command is restricted to the Shalom Support team!".to_string();
     }
 
-    if !command
+    if ticket.is_none()
+        && TicketStore::global()
+            .archived_ticket_by_channel(command.channel_id)
+            .is_ok_and(|archived| archived.is_some())
+    {
+        return "This ticket is already closed, use /reopen to reopen it.".to_string();
+    }
+
+    // Channels from before tickets were stored are recognised by name
+    if ticket.is_none()
+        && !tickets::is_ticket_channel_name(
+            &command
+                .channel_id
+                .name(&ctx.http)
+                .await
+                .unwrap_or("".to_string()),
+        )
+    {
+        return "This command can only be run in a ticket channel!".to_string();
+    }
+
+    let reason = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "reason")
+        .and_then(|option| option.value.as_str());
+
+    if let Some(ticket) = &ticket {
+        if let Err(why) = TicketStore::global().record_event(
+            ticket.id,
+            EventKind::CloseRequested,
+            command.user.id,
+            reason,
+        ) {
+            error!("Error recording close request: {}", why);
+        }
+    }
+
+    let mut content = "Thank you for your ticket with Shalom Support. Please confirm below when you are ready to close this ticket.".to_string();
+    if let Some(reason) = reason {
+        content += &format!("\nReason: {}", reason);
+    }
+
+    if let Err(why) = command
         .channel_id
-        .name(&ctx.http)
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .content(content)
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new(CONFIRM_BUTTON)
+                        .label("Close Ticket")
+                        .style(ButtonStyle::Danger),
+                    CreateButton::new(CANCEL_BUTTON)
+                        .label("Keep Open")
+                        .style(ButtonStyle::Secondary),
+                ])]),
+        )
         .await
-        .unwrap_or("".to_string())
-        .starts_with("ticket")
     {
-        return "This command can only be run in a ticket channel!".to_string();
+        error!("Error sending close confirmation: {}", why);
+        return "Error requesting close".to_string();
+    }
+
+    "Close requested".to_string()
+}
+
+/// Handles the Close Ticket / Keep Open buttons posted by `/close`. Only the ticket author or
+/// the support team may press them.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction) {
+    let store = TicketStore::global();
+    let ticket = match store.open_ticket_by_channel(component.channel_id) {
+        Ok(ticket) => ticket,
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            None
+        }
+    };
+
+    let is_support = component
+        .member
+        .as_ref()
+        .is_some_and(|m| is_staff(&m.roles, ticket.as_ref()));
+    let is_opener = ticket
+        .as_ref()
+        .is_some_and(|ticket| ticket.opener_id == component.user.id);
+    if !is_support && !is_opener {
+        let _ = component
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("Only the ticket author or the Shalom Support team can do that!")
+                        .ephemeral(true),
+                ),
+            )
+            .await;
+        return;
     }
 
-    let msg = command.channel_id.send_message(&ctx.http, CreateMessage::new()
-        .content("Thank you for your ticket with Shalom Support. Please react with an ❌ to indicate you are ready to close this ticket.")).await.expect("error sending message");
-    msg.react(&ctx.http, ReactionType::from('❌'))
+    let confirmed = component.data.custom_id == CONFIRM_BUTTON;
+    if let Some(ticket) = &ticket {
+        let kind = if confirmed {
+            EventKind::CloseConfirmed
+        } else {
+            EventKind::CloseCancelled
+        };
+        if let Err(why) = store.record_event(ticket.id, kind, component.user.id, None) {
+            error!("Error recording close decision: {}", why);
+        }
+    }
+
+    let content = if confirmed {
+        format!("Ticket close confirmed by {}", component.user.mention())
+    } else {
+        format!("Ticket close cancelled by {}", component.user.mention())
+    };
+    if let Err(why) = component
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(Vec::new()),
+            ),
+        )
         .await
-        .expect("error reacting");
+    {
+        error!("Cannot respond to close button: {}", why);
+        return;
+    }
 
-    "".to_string()
+    if confirmed {
+        let reason = match &ticket {
+            Some(ticket) => store
+                .latest_event_detail(ticket.id, EventKind::CloseRequested)
+                .unwrap_or_else(|why| {
+                    error!("Error looking up close reason: {}", why);
+                    None
+                }),
+            None => None,
+        };
+        tickets::close_ticket(
+            ctx,
+            component.channel_id,
+            component.user.id,
+            reason.as_deref(),
+        )
+        .await;
+    }
+}
+
+fn is_staff(roles: &[RoleId], ticket: Option<&Ticket>) -> bool {
+    match ticket {
+        Some(ticket) => tickets::is_ticket_staff(roles, ticket),
+        None => tickets::is_support_team(roles),
+    }
 }
 
 pub fn register() -> CreateCommand {
-    CreateCommand::new("close").description("Close a shalom ticket")
+    CreateCommand::new("close")
+        .description("Close a shalom ticket")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "reason",
+                "why the ticket is being closed",
+            )
+            .max_length(500)
+            .required(false),
+        )
 }
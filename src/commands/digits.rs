@@ -3,9 +3,9 @@ use serenity::all::{CommandDataOption, CreateCommand, CreateCommandOption};
 
 use crate::digits::DigitsSolver;
 
-pub fn run(options: &Vec<CommandDataOption>) -> std::string::String {
+pub fn run(options: &[CommandDataOption]) -> std::string::String {
     let target = options
-        .get(0)
+        .first()
         .expect("expected target")
         .value
         .as_i64()
@@ -35,7 +35,7 @@ pub fn run(options: &Vec<CommandDataOption>) -> std::string::String {
         response = response.replace(", ||", "||");
     }
 
-    return response;
+    response
 }
 
 pub fn register() -> CreateCommand {
@@ -0,0 +1,37 @@
+use log::error;
+use serenity::all::{CommandInteraction, CreateCommand};
+use serenity::prelude::Mentionable;
+
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub fn run(command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    let on_hold = !ticket.on_hold;
+    if let Err(why) = store.set_on_hold(ticket.id, on_hold, command.user.id) {
+        error!("Error recording ticket hold: {}", why);
+        return "Error changing ticket hold".to_string();
+    }
+
+    if on_hold {
+        format!(
+            "{} has put this ticket on hold, it will not be closed for inactivity.",
+            command.user.mention()
+        )
+    } else {
+        format!(
+            "{} has taken this ticket off hold, it will be closed if it becomes inactive.",
+            command.user.mention()
+        )
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("hold")
+        .description("Toggle whether a shalom ticket is kept open when inactive")
+}
@@ -1,3 +1,16 @@
+pub mod claim;
 pub mod close;
 pub mod digits;
+pub mod hold;
+pub mod note;
+pub mod notes;
+pub mod ratings;
+pub mod reopen;
+pub mod reply;
+pub mod snippet;
 pub mod ticket;
+pub mod ticketban;
+pub mod ticketpanel;
+pub mod ticketstats;
+pub mod ticketunban;
+pub mod unclaim;
@@ -0,0 +1,66 @@
+use log::error;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use serenity::client::Context;
+
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+/// Responds privately so the ticket author never sees the note.
+pub async fn run(ctx: &Context, command: &CommandInteraction) {
+    let content = add_note(command);
+    if let Err(why) = command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await
+    {
+        error!("Cannot respond to note command: {}", why);
+    }
+}
+
+fn add_note(command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    let text = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "text")
+        .and_then(|option| option.value.as_str())
+        .expect("expected text");
+
+    match store.add_note(
+        ticket.id,
+        command.user.id,
+        command.user.display_name(),
+        text,
+    ) {
+        Ok(()) => "Note added, only staff can see it.".to_string(),
+        Err(why) => {
+            error!("Error recording ticket note: {}", why);
+            "Error adding note".to_string()
+        }
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("note")
+        .description("Leave a note on a shalom ticket that only staff can see")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "text", "the note")
+                .max_length(1000)
+                .required(true),
+        )
+}
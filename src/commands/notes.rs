@@ -0,0 +1,72 @@
+use log::error;
+use serenity::all::{
+    CommandInteraction, CreateCommand, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage,
+};
+use serenity::client::Context;
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+/// Responds privately so the ticket author never sees the notes.
+pub async fn run(ctx: &Context, command: &CommandInteraction) {
+    let response = match list_notes(command) {
+        Ok(embed) => CreateInteractionResponseMessage::new().embed(embed),
+        Err(message) => CreateInteractionResponseMessage::new().content(message),
+    };
+    if let Err(why) = command
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(response.ephemeral(true)),
+        )
+        .await
+    {
+        error!("Cannot respond to notes command: {}", why);
+    }
+}
+
+fn list_notes(command: &CommandInteraction) -> Result<CreateEmbed, String> {
+    let store = TicketStore::global();
+    let ticket = tickets::staff_ticket(command)?;
+
+    let notes = store.notes(ticket.id).map_err(|why| {
+        error!("Error looking up ticket notes: {}", why);
+        "Error looking up notes".to_string()
+    })?;
+    if notes.is_empty() {
+        return Err("There are no notes on this ticket.".to_string());
+    }
+
+    // Embeds hold at most 25 fields and 6000 characters, so show as many recent notes as fit
+    let mut shown = 0;
+    let mut length = 0;
+    for note in notes.iter().rev().take(25) {
+        length += note.author_name.len() + note.body.len() + 60;
+        if length > 5500 {
+            break;
+        }
+        shown += 1;
+    }
+    let skipped = notes.len() - shown;
+    let mut embed = CreateEmbed::new()
+        .title(format!("Staff Notes for {}", ticket.name()))
+        .color(Color::from(BotConfig::global_cfg().embed_color));
+    if skipped > 0 {
+        embed = embed.description(format!("{} older notes not shown", skipped));
+    }
+    for note in notes.iter().skip(skipped) {
+        embed = embed.field(
+            format!("{} <t:{}:f>", note.author_name, note.created_at.timestamp()),
+            format!("{}\n— {}", note.body, note.author_id.mention()),
+            false,
+        );
+    }
+    Ok(embed)
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("notes").description("List the staff notes on a shalom ticket")
+}
@@ -0,0 +1,264 @@
+use chrono::{TimeDelta, Utc};
+use log::error;
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, CommandInteraction, CommandOptionType, ComponentInteraction,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInputText, CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal,
+    InputTextStyle, ModalInteraction,
+};
+use serenity::client::Context;
+use serenity::model::id::GuildId;
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub const RATING_BUTTON_PREFIX: &str = "ticket_rating:";
+pub const FEEDBACK_MODAL_PREFIX: &str = "ticket_feedback:";
+
+/// The 1-5 buttons sent to the ticket author with their transcript.
+pub fn rating_buttons(ticket_id: i64) -> CreateActionRow {
+    CreateActionRow::Buttons(
+        (1..=5)
+            .map(|score| {
+                CreateButton::new(format!("{}{}:{}", RATING_BUTTON_PREFIX, ticket_id, score))
+                    .label(format!("{} ⭐", score))
+                    .style(ButtonStyle::Secondary)
+            })
+            .collect(),
+    )
+}
+
+/// Records the score the ticket author pressed and asks them for optional feedback.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction) {
+    let Some((ticket_id, score)) = component
+        .data
+        .custom_id
+        .strip_prefix(RATING_BUTTON_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(ticket_id, score)| {
+            let ticket_id = ticket_id.parse::<i64>().ok()?;
+            Some((ticket_id, score.parse::<u8>().ok()?))
+        })
+        .filter(|(_, score)| (1..=5).contains(score))
+    else {
+        error!("Malformed rating button: {}", component.data.custom_id);
+        return;
+    };
+
+    let store = TicketStore::global();
+    let is_opener = match store.ticket(ticket_id) {
+        Ok(ticket) => ticket.is_some_and(|ticket| ticket.opener_id == component.user.id),
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            false
+        }
+    };
+    let response = if !is_opener {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("Only the ticket author can rate it!")
+                .ephemeral(true),
+        )
+    } else if let Err(why) = store.rate_ticket(ticket_id, score) {
+        error!("Error recording ticket rating: {}", why);
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content("Error recording your rating")
+                .ephemeral(true),
+        )
+    } else {
+        CreateInteractionResponse::Modal(
+            CreateModal::new(
+                format!("{}{}", FEEDBACK_MODAL_PREFIX, ticket_id),
+                format!("You rated us {}/5", score),
+            )
+            .components(vec![CreateActionRow::InputText(
+                CreateInputText::new(InputTextStyle::Paragraph, "Any feedback?", "feedback")
+                    .placeholder("Tell us what went well or what we could do better")
+                    .max_length(1000)
+                    .required(false),
+            )]),
+        )
+    };
+
+    if let Err(why) = component.create_response(&ctx.http, response).await {
+        error!("Cannot respond to rating button: {}", why);
+    }
+}
+
+/// Stores the feedback submitted with a rating and removes the rating buttons.
+pub async fn handle_feedback(ctx: &Context, modal: &ModalInteraction) {
+    let Some(ticket_id) = modal
+        .data
+        .custom_id
+        .strip_prefix(FEEDBACK_MODAL_PREFIX)
+        .and_then(|ticket_id| ticket_id.parse::<i64>().ok())
+    else {
+        error!("Malformed feedback form: {}", modal.data.custom_id);
+        return;
+    };
+
+    let feedback = modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| &row.components)
+        .find_map(|component| match component {
+            ActionRowComponent::InputText(input) => input.value.clone(),
+            _ => None,
+        })
+        .filter(|feedback| !feedback.trim().is_empty());
+    if let Some(feedback) = feedback {
+        if let Err(why) = TicketStore::global().set_rating_feedback(ticket_id, &feedback) {
+            error!("Error recording ticket feedback: {}", why);
+        }
+    }
+
+    if let Err(why) = modal
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content("Thank you for rating Shalom Support!")
+                    .components(Vec::new()),
+            ),
+        )
+        .await
+    {
+        error!("Cannot respond to feedback form: {}", why);
+    }
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) {
+    let response = match summary(command) {
+        Ok(embed) => CreateInteractionResponseMessage::new().embed(embed),
+        Err(message) => CreateInteractionResponseMessage::new()
+            .content(message)
+            .ephemeral(true),
+    };
+    if let Err(why) = command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await
+    {
+        error!("Cannot respond to ratings command: {}", why);
+    }
+}
+
+fn summary(command: &CommandInteraction) -> Result<CreateEmbed, String> {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return Err("This command is restricted to the Shalom Support team!".to_string());
+    }
+
+    let days = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "days")
+        .and_then(|option| option.value.as_i64())
+        .unwrap_or(28);
+    let since = Utc::now() - TimeDelta::days(days);
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    let store = TicketStore::global();
+    let log_error = |why: rusqlite::Error| {
+        error!("Error collecting ticket ratings: {}", why);
+        "Error collecting ticket ratings".to_string()
+    };
+    let ratings = store.ratings_since(guild_id, since).map_err(log_error)?;
+    let staff_ratings = store
+        .staff_ratings_since(guild_id, since)
+        .map_err(log_error)?;
+
+    let mut embed = CreateEmbed::new()
+        .title("Ticket Ratings")
+        .description(format!("Last {} days", days))
+        .color(Color::from(BotConfig::global_cfg().embed_color));
+    if ratings.is_empty() {
+        return Ok(embed.field("Ratings", "No ratings", false));
+    }
+
+    let average = ratings
+        .iter()
+        .map(|rating| f64::from(rating.score))
+        .sum::<f64>()
+        / ratings.len() as f64;
+    let distribution = (1..=5)
+        .rev()
+        .map(|score| {
+            let count = ratings
+                .iter()
+                .filter(|rating| rating.score == score)
+                .count();
+            format!("{} ⭐: {}", score, count)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    embed = embed
+        .field("Ratings", ratings.len().to_string(), true)
+        .field("Average", format!("{:.2} / 5", average), true)
+        .field("Distribution", distribution, false);
+
+    let mut per_supporter = String::new();
+    for (user_id, count, average) in staff_ratings {
+        let line = format!(
+            "{}: {:.2} / 5 over {} tickets\n",
+            user_id.mention(),
+            average,
+            count
+        );
+        if per_supporter.len() + line.len() > 1000 {
+            per_supporter += "…";
+            break;
+        }
+        per_supporter += &line;
+    }
+    if !per_supporter.is_empty() {
+        embed = embed.field("Per Supporter", per_supporter, false);
+    }
+
+    let mut feedback = String::new();
+    for rating in ratings.iter().filter(|rating| rating.feedback.is_some()) {
+        let text: String = rating
+            .feedback
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .take(150)
+            .collect();
+        let name = match store.ticket(rating.ticket_id) {
+            Ok(Some(ticket)) => ticket.name(),
+            _ => format!("#{}", rating.ticket_id),
+        };
+        let line = format!("{} ({} ⭐): {}\n", name, rating.score, text);
+        if feedback.len() + line.len() > 1000 {
+            break;
+        }
+        feedback += &line;
+    }
+    if !feedback.is_empty() {
+        embed = embed.field("Recent Feedback", feedback, false);
+    }
+    Ok(embed)
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ratings")
+        .description("Show how ticket authors rated the Shalom Support team")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "days",
+                "how many days back to include (defaults to 28)",
+            )
+            .min_int_value(1)
+            .max_int_value(3650)
+            .required(false),
+        )
+}
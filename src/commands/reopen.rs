@@ -0,0 +1,141 @@
+use chrono::{TimeDelta, Utc};
+use log::error;
+use serenity::all::{
+    ChannelId, CommandInteraction, CreateCommand, EditChannel, EditThread, PermissionOverwrite,
+    PermissionOverwriteType, Permissions,
+};
+use serenity::client::Context;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match store.archived_ticket_by_channel(command.channel_id) {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => {
+            return "This command can only be run in an archived ticket channel!".to_string()
+        }
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            return "Error looking up ticket".to_string();
+        }
+    };
+
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_ticket_staff(&m.roles, &ticket))
+    {
+        return "This command is restricted to the Shalom Support team!".to_string();
+    }
+
+    let reopen_window = TimeDelta::seconds(BotConfig::global_cfg().guild_settings.reopen_window);
+    if ticket
+        .closed_at
+        .is_some_and(|closed_at| closed_at + reopen_window < Utc::now())
+    {
+        return format!(
+            "This ticket was closed more than {} ago and can no longer be reopened.",
+            tickets::format_duration(reopen_window)
+        );
+    }
+
+    // The author of a modmail ticket is kept out of its channel and talks through direct messages
+    if ticket.thread {
+        if let Err(why) = command
+            .channel_id
+            .edit_thread(&ctx.http, EditThread::new().archived(false).locked(false))
+            .await
+        {
+            error!("Error unarchiving ticket thread: {}", why);
+            return "Error reopening ticket".to_string();
+        }
+        if !ticket.modmail {
+            if let Err(why) = command
+                .channel_id
+                .add_thread_member(&ctx.http, ticket.opener_id)
+                .await
+            {
+                error!("Error adding ticket author back to thread: {}", why);
+                return "Error reopening ticket".to_string();
+            }
+        }
+    } else {
+        if !ticket.modmail {
+            if let Err(why) = command
+                .channel_id
+                .create_permission(
+                    &ctx.http,
+                    PermissionOverwrite {
+                        allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+                        deny: Permissions::empty(),
+                        kind: PermissionOverwriteType::Member(ticket.opener_id),
+                    },
+                )
+                .await
+            {
+                error!("Error restoring ticket author permissions: {}", why);
+                return "Error reopening ticket".to_string();
+            }
+        }
+
+        // Archiving removed every member's access, including an exclusive claimer's
+        if let Some(claimer_id) = ticket.claimer_id {
+            let exclusive = match command.channel_id.to_channel(&ctx.http).await {
+                Ok(channel) => channel
+                    .guild()
+                    .is_some_and(|channel| tickets::is_exclusively_claimed(&channel, &ticket)),
+                Err(why) => {
+                    error!("Error fetching ticket channel: {}", why);
+                    false
+                }
+            };
+            if exclusive {
+                if let Err(why) = command
+                    .channel_id
+                    .create_permission(
+                        &ctx.http,
+                        PermissionOverwrite {
+                            allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+                            deny: Permissions::empty(),
+                            kind: PermissionOverwriteType::Member(claimer_id),
+                        },
+                    )
+                    .await
+                {
+                    error!("Error restoring ticket claimer permissions: {}", why);
+                    return "Error reopening ticket".to_string();
+                }
+            }
+        }
+
+        if let Err(why) = command
+            .channel_id
+            .edit(
+                &ctx.http,
+                EditChannel::new().category(ChannelId::new(ticket.topic_settings().category)),
+            )
+            .await
+        {
+            error!("Error moving ticket channel out of archive: {}", why);
+        }
+    }
+
+    if let Err(why) = store.reopen_ticket(ticket.id, command.user.id) {
+        error!("Error recording ticket reopen: {}", why);
+        return "Error reopening ticket".to_string();
+    }
+
+    format!(
+        "{} has reopened this ticket, welcome back {}.",
+        command.user.mention(),
+        ticket.opener_id.mention()
+    )
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("reopen").description("Reopen a closed shalom ticket")
+}
@@ -0,0 +1,53 @@
+use log::{error, warn};
+use serenity::all::{CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    if !ticket.modmail {
+        return "This ticket was not opened by direct message, reply in the channel instead."
+            .to_string();
+    }
+
+    let text = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "text")
+        .and_then(|option| option.value.as_str())
+        .expect("expected text");
+
+    // The bot's own response is ignored as a message, so the reply has to count as activity here
+    if let Err(why) = store.record_activity(ticket.id) {
+        error!("Error recording ticket activity: {}", why);
+    }
+    if let Err(why) = store.record_staff_reply(ticket.id, command.user.id) {
+        error!("Error recording staff reply: {}", why);
+    }
+    // The response stays in the channel, so the reply is part of the transcript
+    match tickets::modmail::relay_to_opener(ctx, &ticket, &command.user, text, &[]).await {
+        Ok(()) => format!("**{}:** {}", command.user.display_name(), text),
+        Err(why) => {
+            warn!("Could not relay reply to modmail opener: {}", why);
+            tickets::modmail::undelivered_notice(&ticket)
+        }
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("reply")
+        .description("Reply to the author of a shalom ticket opened by direct message")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "text", "the reply")
+                .max_length(2000)
+                .required(true),
+        )
+}
@@ -0,0 +1,218 @@
+use log::{error, warn};
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandInteraction, CommandOptionType,
+    CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateInteractionResponse,
+};
+use serenity::client::Context;
+use serenity::model::id::GuildId;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::{Ticket, TicketStore};
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return "This command is restricted to the Shalom Support team!".to_string();
+    }
+
+    let Some(subcommand) = command.data.options.first() else {
+        return "not implemented :(".to_string();
+    };
+    let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+        return "not implemented :(".to_string();
+    };
+    let name = string_option(options, "name").map(|name| name.trim().to_lowercase());
+    let text = string_option(options, "text");
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    let store = TicketStore::global();
+    let result = match (subcommand.name.as_str(), name, text) {
+        ("add", Some(name), Some(text)) => store
+            .add_snippet(guild_id, &name, text, command.user.id)
+            .map(|added| {
+                if added {
+                    format!("Snippet `{}` added.", name)
+                } else {
+                    format!(
+                        "A snippet called `{}` already exists, use /snippet edit.",
+                        name
+                    )
+                }
+            }),
+        ("edit", Some(name), Some(text)) => store
+            .edit_snippet(guild_id, &name, text, command.user.id)
+            .map(|edited| {
+                if edited {
+                    format!("Snippet `{}` updated.", name)
+                } else {
+                    format!("There is no snippet called `{}`.", name)
+                }
+            }),
+        ("remove", Some(name), _) => store.remove_snippet(guild_id, &name).map(|removed| {
+            if removed {
+                format!("Snippet `{}` removed.", name)
+            } else {
+                format!("There is no snippet called `{}`.", name)
+            }
+        }),
+        ("list", _, _) => store.snippet_names(guild_id).map(|names| {
+            if names.is_empty() {
+                "There are no snippets yet, add one with /snippet add.".to_string()
+            } else {
+                let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+                format!("Snippets: {}", names.join(", "))
+            }
+        }),
+        ("send", Some(name), _) => return send(ctx, command, guild_id, &name).await,
+        _ => return "not implemented :(".to_string(),
+    };
+
+    result.unwrap_or_else(|why| {
+        error!("Error managing snippets: {}", why);
+        "Error managing snippets".to_string()
+    })
+}
+
+/// Posts the snippet `name` in the current ticket, relaying it to the author of a modmail ticket.
+async fn send(
+    ctx: &Context,
+    command: &CommandInteraction,
+    guild_id: GuildId,
+    name: &str,
+) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    let body = match store.snippet(guild_id, name) {
+        Ok(Some(body)) => body,
+        Ok(None) => return format!("There is no snippet called `{}`.", name),
+        Err(why) => {
+            error!("Error looking up snippet: {}", why);
+            return "Error looking up snippet".to_string();
+        }
+    };
+
+    let text = fill_template(&body, &ticket, command);
+    // Mentions are longer than the placeholders they replace
+    if text.chars().count() > tickets::MESSAGE_LIMIT {
+        return format!(
+            "Snippet `{}` is too long to send once filled in, shorten it with /snippet edit.",
+            name
+        );
+    }
+
+    // The bot's own response is ignored as a message, so the snippet has to count as activity here
+    if let Err(why) = store.record_activity(ticket.id) {
+        error!("Error recording ticket activity: {}", why);
+    }
+    if let Err(why) = store.record_staff_reply(ticket.id, command.user.id) {
+        error!("Error recording staff reply: {}", why);
+    }
+    if ticket.modmail {
+        if let Err(why) =
+            tickets::modmail::relay_to_opener(ctx, &ticket, &command.user, &text, &[]).await
+        {
+            warn!("Could not relay snippet to modmail opener: {}", why);
+            return tickets::modmail::undelivered_notice(&ticket);
+        }
+    }
+    text
+}
+
+/// Substitutes `{opener}`, `{ticket_id}`, `{topic}` and `{staff}` in a snippet.
+fn fill_template(body: &str, ticket: &Ticket, command: &CommandInteraction) -> String {
+    body.replace("{opener}", &ticket.opener_id.mention().to_string())
+        .replace("{ticket_id}", &ticket.name())
+        .replace("{topic}", &ticket.topic_settings().label)
+        .replace("{staff}", &command.user.mention().to_string())
+}
+
+/// Suggests snippet names matching what has been typed so far.
+pub async fn autocomplete(ctx: &Context, interaction: &CommandInteraction) {
+    let typed = interaction
+        .data
+        .autocomplete()
+        .map(|option| option.value.to_lowercase())
+        .unwrap_or_default();
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    let names = TicketStore::global()
+        .snippet_names(guild_id)
+        .unwrap_or_else(|why| {
+            error!("Error looking up snippets: {}", why);
+            Vec::new()
+        });
+
+    let mut choices = CreateAutocompleteResponse::new();
+    for name in names.iter().filter(|name| name.contains(&typed)).take(25) {
+        choices = choices.add_string_choice(name, name);
+    }
+    if let Err(why) = interaction
+        .create_response(&ctx.http, CreateInteractionResponse::Autocomplete(choices))
+        .await
+    {
+        error!("Cannot respond to snippet autocomplete: {}", why);
+    }
+}
+
+fn string_option<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_str())
+}
+
+pub fn register() -> CreateCommand {
+    let name_option = |autocomplete: bool| {
+        CreateCommandOption::new(CommandOptionType::String, "name", "the snippet's name")
+            .max_length(50)
+            .set_autocomplete(autocomplete)
+            .required(true)
+    };
+    let text_option = || {
+        CreateCommandOption::new(
+            CommandOptionType::String,
+            "text",
+            "the response, may use {opener}, {ticket_id}, {topic} and {staff}",
+        )
+        .max_length(2000)
+        .required(true)
+    };
+
+    CreateCommand::new("snippet")
+        .description("Canned responses for shalom tickets")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "add", "Add a snippet")
+                .add_sub_option(name_option(false))
+                .add_sub_option(text_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "edit", "Change a snippet")
+                .add_sub_option(name_option(true))
+                .add_sub_option(text_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "remove", "Delete a snippet")
+                .add_sub_option(name_option(true)),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List all snippets",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "send",
+                "Post a snippet in this ticket",
+            )
+            .add_sub_option(name_option(true)),
+        )
+}
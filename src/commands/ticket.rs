@@ -1,101 +1,760 @@
-use serenity::all::{CommandInteraction, CreateChannel, CreateCommand, CreateEmbed, CreateMessage};
-use std::collections::HashMap;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use log::error;
+use serenity::all::{
+    ActionRowComponent, AutoArchiveDuration, CommandDataOption, CommandDataOptionValue,
+    CommandInteraction, CommandOptionType, CreateActionRow, CreateAllowedMentions, CreateChannel,
+    CreateCommand, CreateCommandOption, CreateEmbed, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateThread, GuildChannel,
+    InputTextStyle, ModalInteraction,
+};
 
 use serenity::client::Context;
-use serenity::model::channel::{
-    ChannelType, GuildChannel, PermissionOverwrite, PermissionOverwriteType,
-};
-use serenity::model::id::{ChannelId, GuildId, RoleId};
-use serenity::model::Permissions;
+use serenity::model::channel::{ChannelType, PermissionOverwrite, PermissionOverwriteType};
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::model::{Color, Permissions};
 use serenity::prelude::Mentionable;
 
-use crate::botconfig::BotConfig;
+use crate::botconfig::{BotConfig, TicketMode, TicketTopic};
+use crate::tickets;
+use crate::tickets::store::{EventKind, Ticket, TicketStore};
+
+pub const INTAKE_MODAL_PREFIX: &str = "ticket_intake:";
+
+/// The characters a welcome embed may use, below Discord's limit of 6000 so it can still be claimed.
+const WELCOME_EMBED_LIMIT: usize = 5900;
+
+/// The most closed tickets listed by `/ticket search`.
+const SEARCH_RESULTS: usize = 10;
+
+/// Returns the message to respond with, or `None` if the command has already been responded to.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> Option<String> {
+    let Some(subcommand) = command.data.options.first() else {
+        return Some("not implemented :(".to_string());
+    };
+    let CommandDataOptionValue::SubCommand(options) = &subcommand.value else {
+        return Some("not implemented :(".to_string());
+    };
+
+    let response = match subcommand.name.as_str() {
+        "open" => {
+            let guild_settings = &BotConfig::global_cfg().guild_settings;
+            let topic = options
+                .iter()
+                .find(|option| option.name == "topic")
+                .and_then(|option| option.value.as_str())
+                .and_then(|name| guild_settings.topic(name))
+                .unwrap_or_else(|| guild_settings.default_topic());
+            let response = start_open(command.user.id, topic);
+            if let Err(why) = command.create_response(&ctx.http, response).await {
+                error!("Cannot show ticket intake form: {}", why);
+            }
+            return None;
+        }
+        "add" => add_member(ctx, command, options).await,
+        "remove" => remove_member(ctx, command, options).await,
+        "search" => {
+            let content = search(command, options).unwrap_or_else(|message| message);
+            let response = CreateInteractionResponseMessage::new()
+                .content(content)
+                .allowed_mentions(CreateAllowedMentions::new())
+                .ephemeral(true);
+            if let Err(why) = command
+                .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+                .await
+            {
+                error!("Cannot respond to ticket search: {}", why);
+            }
+            return None;
+        }
+        _ => "not implemented :(".to_string(),
+    };
+    Some(response)
+}
+
+/// The first step of opening a ticket about `topic`: shows the intake form, unless `user_id` is
+/// not allowed to open a ticket right now.
+pub fn start_open(user_id: UserId, topic: &TicketTopic) -> CreateInteractionResponse {
+    match refusal_notice(user_id) {
+        Some(notice) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(notice)
+                .ephemeral(true),
+        ),
+        None => CreateInteractionResponse::Modal(intake_form(topic)),
+    }
+}
+
+/// The form asking the configured intake questions before opening a ticket about `topic`.
+fn intake_form(topic: &TicketTopic) -> CreateModal {
+    let rows = BotConfig::global_cfg()
+        .guild_settings
+        .intake_fields
+        .iter()
+        .take(5)
+        .enumerate()
+        .map(|(i, field)| {
+            let style = if field.paragraph {
+                InputTextStyle::Paragraph
+            } else {
+                InputTextStyle::Short
+            };
+            let mut input = CreateInputText::new(style, &field.label, i.to_string())
+                .required(field.required)
+                .max_length(if field.paragraph { 1000 } else { 100 });
+            if let Some(placeholder) = &field.placeholder {
+                input = input.placeholder(placeholder);
+            }
+            CreateActionRow::InputText(input)
+        })
+        .collect();
+
+    let title: String = format!("New {} Ticket", topic.label)
+        .chars()
+        .take(45)
+        .collect();
+    CreateModal::new(format!("{}{}", INTAKE_MODAL_PREFIX, topic.name), title).components(rows)
+}
+
+/// Opens a ticket with the answers submitted through the intake form.
+pub async fn handle_intake(ctx: &Context, modal: &ModalInteraction) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let topic = modal
+        .data
+        .custom_id
+        .strip_prefix(INTAKE_MODAL_PREFIX)
+        .and_then(|name| guild_settings.topic(name))
+        .unwrap_or_else(|| guild_settings.default_topic());
+
+    let mut answers = Vec::new();
+    for row in &modal.data.components {
+        for component in &row.components {
+            let ActionRowComponent::InputText(input) = component else {
+                continue;
+            };
+            let Some(field) = input
+                .custom_id
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| guild_settings.intake_fields.get(i))
+            else {
+                continue;
+            };
+            let answer = input.value.clone().unwrap_or_default();
+            if !answer.trim().is_empty() {
+                answers.push((field.label.clone(), answer));
+            }
+        }
+    }
+
+    // Check again, another ticket may have been opened or a ban issued while the form was open
+    let response = match refusal_notice(modal.user.id) {
+        Some(notice) => CreateInteractionResponseMessage::new()
+            .content(notice)
+            .ephemeral(true),
+        None => CreateInteractionResponseMessage::new()
+            .content(open(ctx, modal.user.id, topic, &answers, false).await),
+    };
+    if let Err(why) = modal
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await
+    {
+        error!("Cannot respond to ticket intake form: {}", why);
+    }
+}
+
+/// Explains why `user_id` cannot open a ticket right now, if they can't: they are banned, already
+/// have as many tickets open as allowed, or opened one too recently.
+pub fn refusal_notice(user_id: UserId) -> Option<String> {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let guild_id = GuildId::new(guild_settings.guild_id);
+    let store = TicketStore::global();
+
+    let ban = store.active_ban(guild_id, user_id).unwrap_or_else(|why| {
+        error!("Error looking up ticket ban: {}", why);
+        None
+    });
+    if let Some(ban) = ban {
+        let mut notice = match ban.expires_at {
+            Some(expires_at) => format!(
+                "You are banned from creating tickets until <t:{}:f>.",
+                expires_at.timestamp()
+            ),
+            None => "You are permanently banned from creating tickets.".to_string(),
+        };
+        if let Some(reason) = ban.reason {
+            notice += &format!("\nReason: {}", reason);
+        }
+        return Some(notice);
+    }
+
+    let open_tickets = store
+        .open_tickets_by_opener(guild_id, user_id)
+        .unwrap_or_else(|why| {
+            error!("Error looking up open tickets: {}", why);
+            Vec::new()
+        });
+    if guild_settings.max_open_tickets > 0 && open_tickets.len() >= guild_settings.max_open_tickets
+    {
+        let channels: Vec<String> = open_tickets
+            .iter()
+            .map(|ticket| ticket.channel_id.mention().to_string())
+            .collect();
+        return Some(format!(
+            "You already have an open ticket at {}, please continue there.",
+            channels.join(", ")
+        ));
+    }
 
-pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let last_opened_at = store
+        .last_opened_at(guild_id, user_id)
+        .unwrap_or_else(|why| {
+            error!("Error looking up last ticket: {}", why);
+            None
+        });
+    if let Some(last_opened_at) = last_opened_at {
+        let available_at = last_opened_at + TimeDelta::seconds(guild_settings.ticket_cooldown);
+        if available_at > Utc::now() {
+            return Some(format!(
+                "You have opened a ticket recently, you can open another <t:{}:R>.",
+                available_at.timestamp()
+            ));
+        }
+    }
+
+    None
+}
+
+/// Continues the numbering of the ticket channels created before tickets were stored, so the
+/// first stored ticket does not reuse the name of a ticket that is still open.
+async fn seed_ticket_counter(ctx: &Context, guild_id: GuildId) -> Result<(), String> {
+    let store = TicketStore::global();
+    if store
+        .has_ticket_counter(guild_id)
+        .map_err(|why| why.to_string())?
+    {
+        return Ok(());
+    }
+
+    let channels = guild_id
+        .channels(&ctx.http)
+        .await
+        .map_err(|why| why.to_string())?;
+    let last_number = channels
+        .values()
+        .filter_map(|channel| tickets::ticket_channel_number(&channel.name))
+        .max()
+        .unwrap_or(0);
+    store
+        .seed_ticket_counter(guild_id, last_number)
+        .map_err(|why| why.to_string())
+}
+
+/// Creates a new ticket channel about `topic` for `opener_id`, including their `answers` to the
+/// intake form as `(question, answer)` pairs. Modmail tickets are held through direct messages, so
+/// the opener is not given access to the channel.
+pub async fn open(
+    ctx: &Context,
+    opener_id: UserId,
+    topic: &TicketTopic,
+    answers: &[(String, String)],
+    modmail: bool,
+) -> String {
     let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
 
-    let mut ticket_number = 1;
-    let channels = match guild_id.channels(&ctx.http).await {
-        Ok(channels) => channels,
-        Err(_) => {
-            return "Error getting channels".to_string();
+    let store = TicketStore::global();
+    if let Err(why) = seed_ticket_counter(ctx, guild_id).await {
+        error!("Error seeding ticket counter: {}", why);
+        return "Error creating ticket".to_string();
+    }
+    let ticket_number = match store.next_ticket_number(guild_id) {
+        Ok(ticket_number) => ticket_number,
+        Err(why) => {
+            error!("Error reserving ticket number: {}", why);
+            return "Error creating ticket".to_string();
+        }
+    };
+
+    let name = format!("{}-{}", topic.channel_prefix, ticket_number);
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let thread = guild_settings.ticket_mode == TicketMode::Thread;
+    let created = if thread {
+        create_ticket_thread(ctx, name, (!modmail).then_some(opener_id)).await
+    } else {
+        create_ticket_channel(ctx, guild_id, topic, name, (!modmail).then_some(opener_id)).await
+    };
+    let ticket_channel = match created {
+        Ok(channel) => channel,
+        Err(why) => {
+            error!("Error creating ticket channel: {}", why);
+            return "Error Creating Channel".to_string();
         }
     };
 
-    while channel_exists(&channels, ticket_number) {
-        ticket_number += 1;
+    let ticket = match store.open_ticket(
+        guild_id,
+        ticket_number,
+        &topic.name,
+        ticket_channel.id,
+        thread,
+        opener_id,
+    ) {
+        Ok(ticket) => Some(ticket),
+        Err(why) => {
+            error!("Error recording new ticket: {}", why);
+            None
+        }
+    };
+
+    if let Some(ticket) = &ticket {
+        if let Err(why) = store.record_answers(ticket.id, answers) {
+            error!("Error recording ticket intake answers: {}", why);
+        }
+        if modmail {
+            if let Err(why) = store.set_modmail(ticket.id) {
+                error!("Error recording modmail ticket: {}", why);
+            }
+        }
+    }
+
+    let opened_at = ticket
+        .as_ref()
+        .map_or_else(Utc::now, |ticket| ticket.opened_at);
+    let embed = welcome_embed(opener_id, &ticket_channel.name, topic, opened_at, answers);
+
+    // Mentioning a role adds its members to a private thread, so every staff role is mentioned
+    let roles = if thread {
+        tickets::staff_roles(topic)
+    } else {
+        vec![RoleId::new(topic.staff_role)]
+    };
+    let mentions: Vec<String> = roles
+        .iter()
+        .map(|role| role.mention().to_string())
+        .collect();
+    let message = ticket_channel
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .content(format!("{} {}", mentions.join(" "), topic.welcome_text))
+                .embed(embed)
+                .allowed_mentions(CreateAllowedMentions::new().roles(roles)),
+        )
+        .await;
+
+    match (ticket, message) {
+        (Some(ticket), Ok(message)) => {
+            if let Err(why) = store.set_welcome_message(ticket.id, message.id) {
+                error!("Error recording ticket welcome message: {}", why);
+            }
+        }
+        (_, Err(why)) => error!("Error sending ticket welcome message: {}", why),
+        _ => {}
     }
 
-    let ticket_channel = match guild_id
+    format!("Ticket created at {}", ticket_channel.mention())
+}
+
+/// Builds the configured welcome embed for a new ticket, filling in its placeholders, followed by
+/// the opener's intake `answers` shortened to fit Discord's embed size limit.
+fn welcome_embed(
+    opener_id: UserId,
+    ticket_id: &str,
+    topic: &TicketTopic,
+    opened_at: DateTime<Utc>,
+    answers: &[(String, String)],
+) -> CreateEmbed {
+    let cfg = BotConfig::global_cfg();
+    let welcome = &cfg.guild_settings.welcome;
+    let fill = |template: &str| {
+        template
+            .replace("{opener}", &opener_id.mention().to_string())
+            .replace("{ticket_id}", ticket_id)
+            .replace("{topic}", &topic.label)
+            .replace(
+                "{opened_at_relative}",
+                &format!("<t:{}:R>", opened_at.timestamp()),
+            )
+            .replace("{opened_at}", &format!("<t:{}:F>", opened_at.timestamp()))
+    };
+
+    let (title, description) = (fill(&welcome.title), fill(&welcome.description));
+    let mut length = title.chars().count() + description.chars().count();
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .color(Color::from(welcome.color.unwrap_or(cfg.embed_color)));
+    if let Some(thumbnail) = &welcome.thumbnail {
+        embed = embed.thumbnail(thumbnail);
+    }
+    for field in &welcome.fields {
+        let (name, value) = (fill(&field.name), fill(&field.value));
+        length += name.chars().count() + value.chars().count();
+        embed = embed.field(name, value, field.inline);
+    }
+
+    // Room is kept for the "Claimed By" field added when the ticket is claimed
+    let mut budget = WELCOME_EMBED_LIMIT.saturating_sub(length);
+    let field_slots = 24usize.saturating_sub(welcome.fields.len());
+    for (question, answer) in answers.iter().take(field_slots) {
+        let question_length = question.chars().count();
+        if budget < question_length + 2 {
+            break;
+        }
+        let max_chars = (budget - question_length).min(1024);
+        let answer = if answer.chars().count() > max_chars {
+            answer.chars().take(max_chars - 1).collect::<String>() + "…"
+        } else {
+            answer.clone()
+        };
+        budget -= question_length + answer.chars().count();
+        embed = embed.field(question, answer, false);
+    }
+    embed
+}
+
+/// Creates a text channel in the topic's category that only the staff and `opener_id` can see.
+async fn create_ticket_channel(
+    ctx: &Context,
+    guild_id: GuildId,
+    topic: &TicketTopic,
+    name: String,
+    opener_id: Option<UserId>,
+) -> serenity::Result<GuildChannel> {
+    let mut permissions = vec![PermissionOverwrite {
+        allow: Permissions::empty(),
+        deny: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+        kind: PermissionOverwriteType::Role(RoleId::new(
+            BotConfig::global_cfg().guild_settings.everyone_role,
+        )),
+    }];
+    for role in tickets::staff_roles(topic) {
+        permissions.push(PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Role(role),
+        });
+    }
+    if let Some(opener_id) = opener_id {
+        permissions.push(PermissionOverwrite {
+            allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+            deny: Permissions::empty(),
+            kind: PermissionOverwriteType::Member(opener_id),
+        });
+    }
+
+    guild_id
         .create_channel(
             &ctx.http,
-            CreateChannel::new(format!("ticket-{}", ticket_number))
+            CreateChannel::new(name)
                 .kind(ChannelType::Text)
-                .category(ChannelId::new(
-                    BotConfig::global_cfg().guild_settings.ticket_category,
-                ))
-                .permissions(vec![
-                    PermissionOverwrite {
-                        allow: Permissions::empty(),
-                        deny: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
-                        kind: PermissionOverwriteType::Role(RoleId::new(
-                            BotConfig::global_cfg().guild_settings.everyone_role,
-                        )),
-                    },
-                    PermissionOverwrite {
-                        allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
-                        deny: Permissions::empty(),
-                        kind: PermissionOverwriteType::Role(RoleId::new(
-                            BotConfig::global_cfg().guild_settings.support_team_role,
-                        )),
-                    },
-                    PermissionOverwrite {
-                        allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
-                        deny: Permissions::empty(),
-                        kind: PermissionOverwriteType::Member(command.user.id),
-                    },
-                ]),
+                .category(ChannelId::new(topic.category))
+                .permissions(permissions),
         )
         .await
-    {
-        Ok(channel) => channel,
-        Err(_) => {
-            return "Error Creating Channel".to_string();
-        }
+}
+
+/// Creates a private thread in `ticket_thread_channel` with `opener_id` in it. Staff join it when
+/// their roles are mentioned in the welcome message, so the bot needs the Mention Everyone
+/// permission unless the staff roles are mentionable.
+async fn create_ticket_thread(
+    ctx: &Context,
+    name: String,
+    opener_id: Option<UserId>,
+) -> serenity::Result<GuildChannel> {
+    let Some(parent) = BotConfig::global_cfg().guild_settings.ticket_thread_channel else {
+        return Err(serenity::Error::Other(
+            "ticket_thread_channel must be set when ticket_mode is thread",
+        ));
     };
+    let parent = ChannelId::new(parent);
+    let thread = parent
+        .create_thread(
+            &ctx.http,
+            CreateThread::new(name)
+                .kind(ChannelType::PrivateThread)
+                .invitable(false)
+                .auto_archive_duration(AutoArchiveDuration::OneWeek),
+        )
+        .await?;
+    if let Some(opener_id) = opener_id {
+        thread.id.add_thread_member(&ctx.http, opener_id).await?;
+    }
+    Ok(thread)
+}
 
-    let _message = ticket_channel.send_message(
-        &ctx.http, CreateMessage::new().embed(CreateEmbed::new().title("New Ticket")
-            .description("Before asking for support on your newly created ticket, please read our simple terms of service.
-•    First of all, keep in mind that tickets may not be private, and may be used for Shalom Support Team ™ training, and also examples for our members.
-•    Second, you should be careful about holding information back. If you cannot provide a full conversation, we cannot provide the best answer for you.
-•    Please note that all tickets may cost you up to several thousands of dollars.
-•    Don’t forget that the Shalom Support Team ™ is completely serious and all of our answers take careful critiquing, and contemplation.
-•    Releasing information about the Shalom Support Team ™ and fellow members of the Shalom Support Group ™, is punishable by full force explosions.
-•    The Shalom Support Team ™ has full control over your ability to create tickets. Our team takes the upmost importance to fulfill your tickets accurately, so please do not fool around with our ticketing service. If you do, we will ban your ticket making ability.
-•    Keep in mind the Shalom Support Team ™ may be offline and not able to answer your ticket at any possible time.
-•    If your ticket is taking time to be processed please be patient. If you have waited over 24 hours then you my contact a Shalom Supporter ™ outside of your ticket.
-•    All tickets are logged in the event that there is a complaint against a member of support staff or a member. If you would like a copy of your ticket's log, let us know.
-")
-            .thumbnail("https://cdn2.iconfinder.com/data/icons/flaturici-set-4/512/ticket-512.png")
-            .field("Ticket Author", command.user.mention().to_string(), true)
-            .field("Ticket ID", &ticket_channel.name, true)
-        ),
-    ).await;
+async fn add_member(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[CommandDataOption],
+) -> String {
+    let (ticket, user_id) = match member_change_target(command, options) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
 
-    format!("Ticket created at {}", ticket_channel.mention().to_string())
+    let added = if ticket.thread {
+        command
+            .channel_id
+            .add_thread_member(&ctx.http, user_id)
+            .await
+    } else {
+        command
+            .channel_id
+            .create_permission(
+                &ctx.http,
+                PermissionOverwrite {
+                    allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+                    deny: Permissions::empty(),
+                    kind: PermissionOverwriteType::Member(user_id),
+                },
+            )
+            .await
+    };
+    if let Err(why) = added {
+        error!("Error adding member to ticket: {}", why);
+        return "Error adding user to ticket".to_string();
+    }
+
+    if let Err(why) = TicketStore::global().record_event(
+        ticket.id,
+        EventKind::MemberAdded,
+        command.user.id,
+        Some(&user_id.to_string()),
+    ) {
+        error!("Error recording ticket member change: {}", why);
+    }
+
+    format!(
+        "{} added {} to this ticket.",
+        command.user.mention(),
+        user_id.mention()
+    )
 }
 
-fn channel_exists(channels: &HashMap<ChannelId, GuildChannel>, ticket_number: i32) -> bool {
-    for channel_entry in channels {
-        if channel_entry.1.name == format!("ticket-{}", ticket_number) {
-            return true;
+async fn remove_member(
+    ctx: &Context,
+    command: &CommandInteraction,
+    options: &[CommandDataOption],
+) -> String {
+    let (ticket, user_id) = match member_change_target(command, options) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    if user_id == ticket.opener_id {
+        return "The ticket author cannot be removed from their ticket.".to_string();
+    }
+    if ticket.claimer_id == Some(user_id) {
+        return "The claimer cannot be removed from this ticket, use /unclaim instead.".to_string();
+    }
+
+    let removed = if ticket.thread {
+        command
+            .channel_id
+            .remove_thread_member(&ctx.http, user_id)
+            .await
+    } else {
+        command
+            .channel_id
+            .delete_permission(&ctx.http, PermissionOverwriteType::Member(user_id))
+            .await
+    };
+    if let Err(why) = removed {
+        error!("Error removing member from ticket: {}", why);
+        return "Error removing user from ticket".to_string();
+    }
+
+    if let Err(why) = TicketStore::global().record_event(
+        ticket.id,
+        EventKind::MemberRemoved,
+        command.user.id,
+        Some(&user_id.to_string()),
+    ) {
+        error!("Error recording ticket member change: {}", why);
+    }
+
+    format!(
+        "{} removed {} from this ticket.",
+        command.user.mention(),
+        user_id.mention()
+    )
+}
+
+/// Checks that `/ticket add` or `/ticket remove` was run in a ticket by its author or the support
+/// team, returning the ticket and the user to add or remove, or else the response to send.
+fn member_change_target(
+    command: &CommandInteraction,
+    options: &[CommandDataOption],
+) -> Result<(Ticket, UserId), String> {
+    let ticket = match TicketStore::global().open_ticket_by_channel(command.channel_id) {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return Err("This command can only be run in a ticket channel!".to_string()),
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            return Err("Error looking up ticket".to_string());
         }
+    };
+
+    let is_support = command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_ticket_staff(&m.roles, &ticket));
+    if !is_support && command.user.id != ticket.opener_id {
+        return Err(
+            "Only the ticket author or the Shalom Support team can change who is in a ticket!"
+                .to_string(),
+        );
     }
 
-    false
+    let user_id = options
+        .iter()
+        .find(|option| option.name == "user")
+        .and_then(|option| option.value.as_user_id())
+        .expect("expected user");
+
+    Ok((ticket, user_id))
+}
+
+/// Finds closed tickets by author, close date and transcript keywords for `/ticket search`,
+/// returning the message listing them.
+fn search(command: &CommandInteraction, options: &[CommandDataOption]) -> Result<String, String> {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return Err("This command is restricted to the Shalom Support team!".to_string());
+    }
+
+    let option = |name: &str| options.iter().find(|option| option.name == name);
+    let user_id = option("user").and_then(|option| option.value.as_user_id());
+    let keywords = option("keywords").and_then(|option| option.value.as_str());
+    let date = |name: &str| -> Result<Option<DateTime<Utc>>, String> {
+        let Some(text) = option(name).and_then(|option| option.value.as_str()) else {
+            return Ok(None);
+        };
+        NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+            .map(|date| Some(date.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+            .map_err(|_| format!("Invalid date \"{}\", use the format YYYY-MM-DD.", text))
+    };
+    // Both dates are inclusive
+    let from = date("from")?;
+    let to = date("to")?.map(|to| to + TimeDelta::days(1));
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    let matches = TicketStore::global()
+        .search_transcripts(guild_id, user_id, from, to, keywords, SEARCH_RESULTS)
+        .map_err(|why| {
+            error!("Error searching ticket transcripts: {}", why);
+            "Error searching ticket transcripts".to_string()
+        })?;
+    if matches.is_empty() {
+        return Ok("No closed tickets match that search.".to_string());
+    }
+
+    let mut response = String::new();
+    for found in matches {
+        let ticket = &found.ticket;
+        let mut entry = format!("**{}** by {}", ticket.name(), ticket.opener_id.mention());
+        if let Some(closed_at) = ticket.closed_at {
+            entry += &format!(", closed <t:{}:d>", closed_at.timestamp());
+        }
+        if let Some((channel_id, message_id)) = found.log_message {
+            entry += &format!(" · [log]({})", message_id.link(channel_id, Some(guild_id)));
+        }
+        if let Some(snippet) = &found.snippet {
+            entry += &format!("\n> {}", snippet.replace('\n', " "));
+        }
+        entry.push('\n');
+        if response.len() + entry.len() > 1900 {
+            response += "…";
+            break;
+        }
+        response += &entry;
+    }
+    Ok(response)
 }
 
 pub fn register() -> CreateCommand {
-    CreateCommand::new("ticket").description("Create a Shalom Support ticket")
+    let mut topic_option = CreateCommandOption::new(
+        CommandOptionType::String,
+        "topic",
+        "what your ticket is about",
+    )
+    .required(true);
+    for topic in &BotConfig::global_cfg().guild_settings.ticket_topics {
+        topic_option = topic_option.add_string_choice(&topic.label, &topic.name);
+    }
+
+    CreateCommand::new("ticket")
+        .description("Shalom Support tickets")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "open",
+                "Create a Shalom Support ticket",
+            )
+            .add_sub_option(topic_option),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Add a user to this ticket",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::User, "user", "the user to add")
+                    .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Remove a user from this ticket",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::User, "user", "the user to remove")
+                    .required(true),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "search",
+                "Search the transcripts of closed tickets",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "keywords",
+                    "words the transcript must contain",
+                )
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::User,
+                    "user",
+                    "the author of the ticket",
+                )
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "from",
+                    "closed on or after this date (YYYY-MM-DD)",
+                )
+                .required(false),
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "to",
+                    "closed on or before this date (YYYY-MM-DD)",
+                )
+                .required(false),
+            ),
+        )
 }
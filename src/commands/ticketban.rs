@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use log::error;
+use serenity::all::{CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption};
+use serenity::model::id::GuildId;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub fn run(command: &CommandInteraction) -> String {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return "This command is restricted to the Shalom Support team!".to_string();
+    }
+
+    let user_id = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "user")
+        .and_then(|option| option.value.as_user_id())
+        .expect("expected user");
+    let reason = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "reason")
+        .and_then(|option| option.value.as_str());
+    let expires_at = match command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "duration")
+        .and_then(|option| option.value.as_str())
+    {
+        Some(text) => match ban_expiry(text, Utc::now()) {
+            Ok(expires_at) => Some(expires_at),
+            Err(response) => return response,
+        },
+        None => None,
+    };
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    if let Err(why) =
+        TicketStore::global().ban_user(guild_id, user_id, command.user.id, reason, expires_at)
+    {
+        error!("Error recording ticket ban: {}", why);
+        return "Error banning user".to_string();
+    }
+
+    let mut response = match expires_at {
+        Some(expires_at) => format!(
+            "{} is banned from creating tickets until <t:{}:f>.",
+            user_id.mention(),
+            expires_at.timestamp()
+        ),
+        None => format!("{} is banned from creating tickets.", user_id.mention()),
+    };
+    if let Some(reason) = reason {
+        response += &format!("\nReason: {}", reason);
+    }
+    response
+}
+
+/// When a ban for the duration `text` starting at `now` ends, or else the response to send.
+fn ban_expiry(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let Some(duration) = tickets::parse_duration(text) else {
+        return Err(format!(
+            "Invalid duration \"{}\", try something like 12h, 7d or 2w.",
+            text
+        ));
+    };
+    now.checked_add_signed(duration).ok_or_else(|| {
+        format!(
+            "The duration \"{}\" is too long, leave it out for a permanent ban.",
+            text
+        )
+    })
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ticketban")
+        .description("Ban a user from creating shalom tickets")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "the user to ban")
+                .required(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "duration",
+                "how long to ban them for, e.g. 12h, 7d or 2w (permanent if not given)",
+            )
+            .max_length(10)
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "reason", "why they are banned")
+                .max_length(500)
+                .required(false),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, Utc};
+
+    use super::ban_expiry;
+
+    #[test]
+    fn ban_expiry_adds_the_duration() {
+        let now = Utc::now();
+        assert_eq!(ban_expiry("7d", now), Ok(now + TimeDelta::days(7)));
+        assert_eq!(ban_expiry("12H", now), Ok(now + TimeDelta::hours(12)));
+    }
+
+    #[test]
+    fn ban_expiry_rejects_invalid_durations() {
+        let now = Utc::now();
+        for text in ["", "d", "0d", "-3d", "5x", "twod"] {
+            assert!(ban_expiry(text, now).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn ban_expiry_rejects_durations_that_overflow() {
+        let now = Utc::now();
+        assert!(ban_expiry("999999999w", now).is_err());
+        assert!(ban_expiry("99999999d", now).is_err());
+    }
+}
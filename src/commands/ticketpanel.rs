@@ -0,0 +1,104 @@
+use log::error;
+use serenity::all::{
+    ButtonStyle, ChannelType, CommandInteraction, CommandOptionType, ComponentInteraction,
+    CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateMessage,
+    Permissions,
+};
+use serenity::client::Context;
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::commands::ticket;
+
+/// Followed by the topic name. These IDs are stable so panels keep working across restarts.
+pub const OPEN_BUTTON_PREFIX: &str = "ticket_panel_open:";
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    if !command.member.as_ref().is_some_and(|m| {
+        m.permissions
+            .is_some_and(|permissions| permissions.manage_guild())
+    }) {
+        return "This command is restricted to server managers!".to_string();
+    }
+
+    let channel_id = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "channel")
+        .and_then(|option| option.value.as_channel_id())
+        .unwrap_or(command.channel_id);
+
+    if let Err(why) = channel_id.send_message(&ctx.http, panel()).await {
+        error!("Error posting ticket panel: {}", why);
+        return "Error posting ticket panel".to_string();
+    }
+    format!("Ticket panel posted in {}", channel_id.mention())
+}
+
+/// The panel embed with an "Open ticket" button for every topic.
+fn panel() -> CreateMessage {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let buttons: Vec<CreateButton> = guild_settings
+        .ticket_topics
+        .iter()
+        .map(|topic| {
+            let label = if guild_settings.ticket_topics.len() == 1 {
+                "Open ticket".to_string()
+            } else {
+                format!("Open ticket: {}", topic.label)
+            };
+            CreateButton::new(format!("{}{}", OPEN_BUTTON_PREFIX, topic.name))
+                .label(label.chars().take(80).collect::<String>())
+                .style(ButtonStyle::Primary)
+                .emoji('🎫')
+        })
+        .collect();
+    let rows = buttons
+        .chunks(5)
+        .take(5)
+        .map(|buttons| CreateActionRow::Buttons(buttons.to_vec()))
+        .collect();
+
+    CreateMessage::new()
+        .embed(
+            CreateEmbed::new()
+                .title(&guild_settings.panel.title)
+                .description(&guild_settings.panel.text)
+                .color(Color::from(BotConfig::global_cfg().embed_color)),
+        )
+        .components(rows)
+}
+
+/// Opens a ticket about the topic of the pressed panel button, exactly like `/ticket open`.
+pub async fn handle_button(ctx: &Context, component: &ComponentInteraction) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let topic = component
+        .data
+        .custom_id
+        .strip_prefix(OPEN_BUTTON_PREFIX)
+        .and_then(|name| guild_settings.topic(name))
+        .unwrap_or_else(|| guild_settings.default_topic());
+    if let Err(why) = component
+        .create_response(&ctx.http, ticket::start_open(component.user.id, topic))
+        .await
+    {
+        error!("Cannot show ticket intake form: {}", why);
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ticketpanel")
+        .description("Post a panel with buttons to open shalom tickets")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "channel",
+                "where to post the panel (defaults to this channel)",
+            )
+            .channel_types(vec![ChannelType::Text])
+            .required(false),
+        )
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeDelta, Utc};
+use log::error;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use serenity::client::Context;
+use serenity::model::id::{GuildId, UserId};
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::{EventKind, TicketStore};
+
+/// Number of days covered when no start date is given.
+const DEFAULT_RANGE_DAYS: u64 = 28;
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) {
+    let bot_id = ctx.cache.current_user().id;
+    let response = match stats(command, bot_id) {
+        Ok(embed) => CreateInteractionResponseMessage::new().embed(embed),
+        Err(message) => CreateInteractionResponseMessage::new()
+            .content(message)
+            .ephemeral(true),
+    };
+    if let Err(why) = command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(response))
+        .await
+    {
+        error!("Cannot respond to ticket stats command: {}", why);
+    }
+}
+
+/// `bot_id` is left out of the per-supporter breakdown, as the bot closes inactive tickets.
+fn stats(command: &CommandInteraction, bot_id: UserId) -> Result<CreateEmbed, String> {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return Err("This command is restricted to the Shalom Support team!".to_string());
+    }
+
+    let to_date = match date_option(command, "to")? {
+        Some(date) => date,
+        None => Utc::now().date_naive(),
+    };
+    let from_date = match date_option(command, "from")? {
+        Some(date) => date,
+        None => to_date - Days::new(DEFAULT_RANGE_DAYS - 1),
+    };
+    if from_date > to_date {
+        return Err("The start date must not be after the end date.".to_string());
+    }
+    // Both dates are inclusive
+    let from = from_date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let to = (to_date + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    let store = TicketStore::global();
+    let log_error = |why: rusqlite::Error| {
+        error!("Error collecting ticket stats: {}", why);
+        "Error collecting ticket stats".to_string()
+    };
+    let opened = store
+        .tickets_opened_between(guild_id, from, to)
+        .map_err(log_error)?;
+    let closed = store
+        .tickets_closed_between(guild_id, from, to)
+        .map_err(log_error)?;
+    let replies = store
+        .event_counts_by_actor(guild_id, EventKind::StaffReplied, from, to)
+        .map_err(log_error)?;
+    let closes = store
+        .event_counts_by_actor(guild_id, EventKind::Closed, from, to)
+        .map_err(log_error)?;
+
+    let mut weeks: BTreeMap<NaiveDate, (usize, usize)> = BTreeMap::new();
+    for ticket in &opened {
+        weeks.entry(week_start(ticket.opened_at)).or_default().0 += 1;
+    }
+    for closed_at in closed.iter().filter_map(|ticket| ticket.closed_at) {
+        weeks.entry(week_start(closed_at)).or_default().1 += 1;
+    }
+    let mut weekly = String::new();
+    for (week, (opened, closed)) in &weeks {
+        let line = format!("Week of {}: {} opened, {} closed\n", week, opened, closed);
+        if weekly.len() + line.len() > 1000 {
+            weekly += "…";
+            break;
+        }
+        weekly += &line;
+    }
+    if weekly.is_empty() {
+        weekly = "No tickets".to_string();
+    }
+
+    let mut first_responses: Vec<TimeDelta> = opened
+        .iter()
+        .filter_map(|ticket| {
+            ticket
+                .first_staff_reply_at
+                .map(|first_staff_reply_at| first_staff_reply_at - ticket.opened_at)
+        })
+        .collect();
+    first_responses.sort();
+    let first_response = match (
+        percentile(&first_responses, 50),
+        percentile(&first_responses, 90),
+    ) {
+        (Some(median), Some(p90)) => format!(
+            "Median {}, p90 {}\n{} of {} tickets answered",
+            tickets::format_duration(median),
+            tickets::format_duration(p90),
+            first_responses.len(),
+            opened.len()
+        ),
+        _ => "No staff replies".to_string(),
+    };
+
+    let resolutions: Vec<TimeDelta> = closed
+        .iter()
+        .filter_map(|ticket| {
+            ticket
+                .closed_at
+                .map(|closed_at| closed_at - ticket.opened_at)
+        })
+        .collect();
+    let resolution = match i32::try_from(resolutions.len()) {
+        Ok(count) if count > 0 => {
+            tickets::format_duration(resolutions.iter().copied().sum::<TimeDelta>() / count)
+        }
+        _ => "No tickets closed".to_string(),
+    };
+
+    // Every supporter who replied to or closed a ticket, most replies first
+    let mut supporters: Vec<(UserId, u64, u64)> = replies
+        .iter()
+        .map(|(user_id, count)| (*user_id, *count, 0))
+        .collect();
+    for (user_id, count) in closes.into_iter().filter(|(user_id, _)| *user_id != bot_id) {
+        match supporters.iter_mut().find(|(id, _, _)| *id == user_id) {
+            Some(supporter) => supporter.2 = count,
+            None => supporters.push((user_id, 0, count)),
+        }
+    }
+    let mut per_supporter = String::new();
+    for (user_id, replies, closes) in supporters {
+        let line = format!(
+            "{}: {} replies, {} closes\n",
+            user_id.mention(),
+            replies,
+            closes
+        );
+        if per_supporter.len() + line.len() > 1000 {
+            per_supporter += "…";
+            break;
+        }
+        per_supporter += &line;
+    }
+    if per_supporter.is_empty() {
+        per_supporter = "Nobody".to_string();
+    }
+
+    Ok(CreateEmbed::new()
+        .title("Ticket Statistics")
+        .description(format!(
+            "<t:{}:D> to <t:{}:D>",
+            from.timestamp(),
+            (to - TimeDelta::seconds(1)).timestamp()
+        ))
+        .color(Color::from(BotConfig::global_cfg().embed_color))
+        .field("Opened", opened.len().to_string(), true)
+        .field("Closed", closed.len().to_string(), true)
+        .field("Average Resolution Time", resolution, true)
+        .field("First Response Time", first_response, false)
+        .field("Per Week", weekly, false)
+        .field("Per Supporter", per_supporter, false))
+}
+
+/// Parses the date option `name`, given as `YYYY-MM-DD`.
+fn date_option(command: &CommandInteraction, name: &str) -> Result<Option<NaiveDate>, String> {
+    let Some(text) = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_str())
+    else {
+        return Ok(None);
+    };
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| format!("Invalid date \"{}\", use the format YYYY-MM-DD.", text))
+}
+
+/// The Monday starting the week `at` is in.
+fn week_start(at: DateTime<Utc>) -> NaiveDate {
+    let date = at.date_naive();
+    date - Days::new(date.weekday().num_days_from_monday().into())
+}
+
+/// The nearest-rank `percent`th percentile of the sorted `values`.
+fn percentile(values: &[TimeDelta], percent: usize) -> Option<TimeDelta> {
+    let rank = (values.len() * percent).div_ceil(100);
+    values.get(rank.saturating_sub(1)).copied()
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ticketstats")
+        .description("Show shalom ticket statistics for the support team")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "from",
+                "first day to include, as YYYY-MM-DD (defaults to 4 weeks ago)",
+            )
+            .required(false),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "to",
+                "last day to include, as YYYY-MM-DD (defaults to today)",
+            )
+            .required(false),
+        )
+}
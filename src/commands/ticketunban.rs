@@ -0,0 +1,45 @@
+use log::error;
+use serenity::all::{CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption};
+use serenity::model::id::GuildId;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub fn run(command: &CommandInteraction) -> String {
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| tickets::is_support_team(&m.roles))
+    {
+        return "This command is restricted to the Shalom Support team!".to_string();
+    }
+
+    let user_id = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "user")
+        .and_then(|option| option.value.as_user_id())
+        .expect("expected user");
+
+    let guild_id = GuildId::new(BotConfig::global_cfg().guild_settings.guild_id);
+    match TicketStore::global().unban_user(guild_id, user_id) {
+        Ok(true) => format!("{} can create tickets again.", user_id.mention()),
+        Ok(false) => format!("{} is not banned from creating tickets.", user_id.mention()),
+        Err(why) => {
+            error!("Error removing ticket ban: {}", why);
+            "Error unbanning user".to_string()
+        }
+    }
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("ticketunban")
+        .description("Allow a banned user to create shalom tickets again")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::User, "user", "the user to unban")
+                .required(true),
+        )
+}
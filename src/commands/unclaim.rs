@@ -0,0 +1,80 @@
+use log::error;
+use serenity::all::{
+    CommandInteraction, CreateCommand, PermissionOverwrite, PermissionOverwriteType, Permissions,
+};
+use serenity::client::Context;
+use serenity::prelude::Mentionable;
+
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let store = TicketStore::global();
+    let ticket = match tickets::staff_ticket(command) {
+        Ok(ticket) => ticket,
+        Err(response) => return response,
+    };
+
+    let Some(claimer_id) = ticket.claimer_id else {
+        return "This ticket is not claimed.".to_string();
+    };
+
+    let was_exclusive = match command.channel_id.to_channel(&ctx.http).await {
+        Ok(channel) => channel
+            .guild()
+            .is_some_and(|channel| tickets::is_exclusively_claimed(&channel, &ticket)),
+        Err(why) => {
+            error!("Error fetching ticket channel: {}", why);
+            false
+        }
+    };
+    if was_exclusive {
+        for role in tickets::staff_roles(ticket.topic_settings()) {
+            let restored = command
+                .channel_id
+                .create_permission(
+                    &ctx.http,
+                    PermissionOverwrite {
+                        allow: Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES,
+                        deny: Permissions::empty(),
+                        kind: PermissionOverwriteType::Role(role),
+                    },
+                )
+                .await;
+            if let Err(why) = restored {
+                error!("Error restoring staff permissions: {}", why);
+                return "Error restoring ticket permissions".to_string();
+            }
+        }
+        if claimer_id != ticket.opener_id {
+            if let Err(why) = command
+                .channel_id
+                .delete_permission(&ctx.http, PermissionOverwriteType::Member(claimer_id))
+                .await
+            {
+                error!("Error removing claimer permissions: {}", why);
+            }
+        }
+    }
+
+    let ticket = match store.set_claimer(ticket.id, None, command.user.id) {
+        Ok(ticket) => ticket,
+        Err(why) => {
+            error!("Error recording ticket unclaim: {}", why);
+            return "Error unclaiming ticket".to_string();
+        }
+    };
+
+    if let Err(why) = tickets::refresh_welcome_embed(ctx, command.channel_id, &ticket).await {
+        error!("Error updating ticket welcome message: {}", why);
+    }
+
+    format!(
+        "{} has unclaimed this ticket, any supporter can now pick it up.",
+        command.user.mention()
+    )
+}
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("unclaim").description("Release ownership of a shalom ticket")
+}
@@ -51,16 +51,15 @@ struct CrosswordWatcher {
 
 impl CrosswordWatcher {
     async fn check_crossword(&mut self) {
-        let date = query_nyt_latest_puzzle_date().await;
-        if date.is_err() {
-            warn!(
-                "Failed to get latest crossword from NYT: {}",
-                date.unwrap_err()
-            );
-            return;
-        }
-        if !&self.is_already_sent(date.as_ref().unwrap()).await {
-            self.send_crossword_message(date.unwrap()).await;
+        let date = match query_nyt_latest_puzzle_date().await {
+            Ok(date) => date,
+            Err(why) => {
+                warn!("Failed to get latest crossword from NYT: {}", why);
+                return;
+            }
+        };
+        if !&self.is_already_sent(&date).await {
+            self.send_crossword_message(date).await;
         }
     }
 
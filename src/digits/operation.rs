@@ -43,7 +43,7 @@ impl OperationType {
             }
             OperationType::Multiply => Some(num1 * num2),
             OperationType::Divide => {
-                if num1 % num2 == 0 {
+                if num1.is_multiple_of(num2) {
                     Some(num1 / num2)
                 } else {
                     None
@@ -1,18 +1,18 @@
-use log::{error, info};
-use serenity::all::{CreateAttachment, CreateInteractionResponse, CreateMessage, Interaction};
+use log::{error, info, warn};
+use serenity::all::{CreateInteractionResponse, Interaction};
 use serenity::async_trait;
 use serenity::builder::CreateInteractionResponseMessage;
 use serenity::client::{Context, EventHandler};
-use serenity::futures::StreamExt;
-use serenity::model::channel::Reaction;
+use serenity::model::channel::Message;
 use std::sync::atomic::Ordering::Relaxed;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::botconfig::BotConfig;
 use serenity::model::gateway::Ready;
-use serenity::model::id::{ChannelId, GuildId, RoleId};
+use serenity::model::id::GuildId;
 
-use crate::{commands, crossword};
+use crate::tickets::store::TicketStore;
+use crate::{commands, crossword, tickets};
 
 pub struct Handler {
     pub(crate) is_watch_running: AtomicBool,
@@ -20,73 +20,66 @@ pub struct Handler {
 
 #[async_trait]
 impl EventHandler for Handler {
-    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
-        if add_reaction
-            .user_id
-            .is_some_and(|id| id.eq(&ctx.cache.current_user().id))
-        {
+    async fn message(&self, ctx: Context, new_message: Message) {
+        if new_message.author.bot {
             return;
         }
 
-        if !add_reaction.emoji.unicode_eq("❌") {
-            return;
-        }
-
-        if !add_reaction.member.as_ref().is_some_and(|member| {
-            member.roles.contains(&RoleId::new(
-                BotConfig::global_cfg().guild_settings.support_team_role,
-            ))
-        }) {
+        if new_message.guild_id.is_none() {
+            if BotConfig::global_cfg().guild_settings.modmail {
+                tickets::modmail::handle_direct_message(&ctx, &new_message).await;
+            }
             return;
         }
 
-        if add_reaction.message(&ctx.http).await.is_ok_and(|msg| {
-            msg.content
-                .starts_with("Thank you for your ticket with Shalom Support")
-                && msg.author.id.eq(&ctx.cache.current_user().id)
-        }) {
-            let mut message_vec = Vec::new();
-            let mut messages = add_reaction.channel_id.messages_iter(&ctx.http).boxed();
-            while let Some(message_result) = messages.next().await {
-                match message_result {
-                    Ok(message) => message_vec.insert(
-                        0,
-                        format!(
-                            "[{}] {}: {}",
-                            message.timestamp.to_string(),
-                            message.author.name,
-                            message.content
-                        ),
-                    ),
-                    Err(_) => {}
+        let store = TicketStore::global();
+        match store.open_ticket_by_channel(new_message.channel_id) {
+            Ok(Some(ticket)) => {
+                if let Err(why) = store.record_activity(ticket.id) {
+                    error!("Error recording ticket activity: {}", why);
                 }
-            }
-
-            ChannelId::new(BotConfig::global_cfg().guild_settings.ticket_log_channel)
-                .send_message(
-                    &ctx.http,
-                    CreateMessage::new()
-                        .content("Ticket Logged")
-                        .add_file(CreateAttachment::bytes(message_vec.join("\n"), "log.txt")),
-                )
-                .await
-                .unwrap();
-
-            match add_reaction.channel_id.delete(&ctx.http).await {
-                Ok(_) => {
-                    info!("Ticket Channel Deleted")
+                if !new_message
+                    .member
+                    .as_ref()
+                    .is_some_and(|member| tickets::is_ticket_staff(&member.roles, &ticket))
+                {
+                    if let Err(why) = store.record_author_message(ticket.id) {
+                        error!("Error recording ticket author message: {}", why);
+                    }
+                    return;
+                }
+                if let Err(why) = store.record_staff_reply(ticket.id, new_message.author.id) {
+                    error!("Error recording staff reply: {}", why);
                 }
-                Err(error) => {
-                    error!("Error Deleting Ticket Channel: {}", error)
+                if ticket.modmail {
+                    if let Err(why) = tickets::modmail::relay_to_opener(
+                        &ctx,
+                        &ticket,
+                        &new_message.author,
+                        &new_message.content,
+                        &new_message.attachments,
+                    )
+                    .await
+                    {
+                        warn!("Could not relay staff reply to modmail opener: {}", why);
+                        let _ = new_message
+                            .reply(&ctx.http, tickets::modmail::undelivered_notice(&ticket))
+                            .await;
+                    }
                 }
             }
-        };
+            Ok(None) => {}
+            Err(why) => error!("Error looking up ticket: {}", why),
+        }
     }
 
     async fn ready(&self, ctx: Context, ready: Ready) {
         if !self.is_watch_running.load(Ordering::Relaxed) {
             info!("{} is connected!", ready.user.name);
             crossword::start_crossword_watch(Context::clone(&ctx)).await;
+            tickets::inactivity::start_inactivity_watch(Context::clone(&ctx)).await;
+            tickets::sla::start_sla_watch(Context::clone(&ctx)).await;
+            tickets::retention::start_retention_watch(Context::clone(&ctx)).await;
             self.is_watch_running.store(true, Relaxed);
         }
 
@@ -99,6 +92,19 @@ impl EventHandler for Handler {
                 commands::digits::register(),
                 commands::ticket::register(),
                 commands::close::register(),
+                commands::claim::register(),
+                commands::unclaim::register(),
+                commands::hold::register(),
+                commands::reopen::register(),
+                commands::ticketban::register(),
+                commands::ticketunban::register(),
+                commands::ticketstats::register(),
+                commands::ticketpanel::register(),
+                commands::ratings::register(),
+                commands::note::register(),
+                commands::reply::register(),
+                commands::snippet::register(),
+                commands::notes::register(),
             ],
         )
         .await;
@@ -110,13 +116,81 @@ impl EventHandler for Handler {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Component(component) = &interaction {
+            match component.data.custom_id.as_str() {
+                commands::close::CONFIRM_BUTTON | commands::close::CANCEL_BUTTON => {
+                    commands::close::handle_button(&ctx, component).await
+                }
+                custom_id if custom_id.starts_with(commands::ticketpanel::OPEN_BUTTON_PREFIX) => {
+                    commands::ticketpanel::handle_button(&ctx, component).await
+                }
+                custom_id if custom_id.starts_with(commands::ratings::RATING_BUTTON_PREFIX) => {
+                    commands::ratings::handle_button(&ctx, component).await
+                }
+                _ => warn!(
+                    "Unknown component interaction: {}",
+                    component.data.custom_id
+                ),
+            }
+        }
+
+        if let Interaction::Autocomplete(autocomplete) = &interaction {
+            if autocomplete.data.name == "snippet" {
+                commands::snippet::autocomplete(&ctx, autocomplete).await;
+            }
+        }
+
+        if let Interaction::Modal(modal) = &interaction {
+            if modal
+                .data
+                .custom_id
+                .starts_with(commands::ticket::INTAKE_MODAL_PREFIX)
+            {
+                commands::ticket::handle_intake(&ctx, modal).await;
+            } else if modal
+                .data
+                .custom_id
+                .starts_with(commands::ratings::FEEDBACK_MODAL_PREFIX)
+            {
+                commands::ratings::handle_feedback(&ctx, modal).await;
+            }
+        }
+
         if let Interaction::Command(command) = interaction {
             println!("Received command interaction: {:#?}", command);
 
             let content = match command.data.name.as_str() {
                 "digits" => commands::digits::run(&command.data.options),
-                "ticket" => commands::ticket::run(&ctx, &command).await,
+                "ticket" => match commands::ticket::run(&ctx, &command).await {
+                    Some(content) => content,
+                    None => return,
+                },
                 "close" => commands::close::run(&ctx, &command).await,
+                "claim" => commands::claim::run(&ctx, &command).await,
+                "unclaim" => commands::unclaim::run(&ctx, &command).await,
+                "hold" => commands::hold::run(&command),
+                "reply" => commands::reply::run(&ctx, &command).await,
+                "snippet" => commands::snippet::run(&ctx, &command).await,
+                "reopen" => commands::reopen::run(&ctx, &command).await,
+                "ticketban" => commands::ticketban::run(&command),
+                "ticketunban" => commands::ticketunban::run(&command),
+                "ticketpanel" => commands::ticketpanel::run(&ctx, &command).await,
+                "ticketstats" => {
+                    commands::ticketstats::run(&ctx, &command).await;
+                    return;
+                }
+                "ratings" => {
+                    commands::ratings::run(&ctx, &command).await;
+                    return;
+                }
+                "note" => {
+                    commands::note::run(&ctx, &command).await;
+                    return;
+                }
+                "notes" => {
+                    commands::notes::run(&ctx, &command).await;
+                    return;
+                }
                 _ => "not implemented :(".to_string(),
             };
 
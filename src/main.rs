@@ -4,6 +4,7 @@ use std::sync::atomic::AtomicBool;
 use serenity::prelude::*;
 
 use crate::botconfig::BotConfig;
+use crate::tickets::store::TicketStore;
 use events::Handler;
 
 mod botconfig;
@@ -11,6 +12,7 @@ mod commands;
 mod crossword;
 mod digits;
 mod events;
+mod tickets;
 
 #[tokio::main]
 async fn main() {
@@ -20,9 +22,12 @@ async fn main() {
     info!("Loading config...");
     let config = BotConfig::global_cfg();
 
+    info!("Opening ticket store...");
+    TicketStore::global();
+
     let intents = GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::MESSAGE_CONTENT
-        | GatewayIntents::GUILD_MESSAGE_REACTIONS;
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT;
 
     info!("Creating client...");
     let mut client = Client::builder(&config.discord_token, intents)
@@ -0,0 +1,97 @@
+use std::io::{Cursor, Write};
+
+use log::error;
+use serenity::all::{Attachment, Message};
+use zip::result::ZipResult;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Bytes reserved for the end of central directory record in each archive.
+const ARCHIVE_OVERHEAD: u64 = 22;
+
+/// The attachments of a ticket, bundled into zip archives that each fit within an upload limit.
+pub struct AttachmentArchive {
+    pub(crate) parts: Vec<Vec<u8>>,
+    /// Attachments that could not be archived, either because they failed to download or are
+    /// individually too large to upload, in the form `filename (url)`.
+    pub(crate) skipped: Vec<String>,
+}
+
+/// Downloads every attachment in `messages` and bundles them into zip archives no larger than
+/// `upload_limit` bytes each. Files are stored uncompressed, since most attachments are already
+/// compressed images, which keeps archive sizes predictable.
+pub async fn archive_attachments(messages: &[Message], upload_limit: u64) -> AttachmentArchive {
+    let mut archive = AttachmentArchive {
+        parts: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let mut part: Option<(ZipWriter<Cursor<Vec<u8>>>, u64)> = None;
+
+    for message in messages {
+        for attachment in &message.attachments {
+            let name = format!("{}-{}", message.id, attachment.filename);
+            let entry_size = u64::from(attachment.size) + entry_overhead(&name);
+            if entry_size + ARCHIVE_OVERHEAD > upload_limit {
+                archive.skipped.push(describe(attachment));
+                continue;
+            }
+
+            let bytes = match attachment.download().await {
+                Ok(bytes) => bytes,
+                Err(why) => {
+                    error!("Error downloading attachment {}: {}", attachment.url, why);
+                    archive.skipped.push(describe(attachment));
+                    continue;
+                }
+            };
+
+            if part
+                .as_ref()
+                .is_some_and(|(_, size)| size + entry_size > upload_limit)
+            {
+                let (writer, _) = part.take().unwrap();
+                finish_part(&mut archive, writer);
+            }
+            let (writer, size) = part
+                .get_or_insert_with(|| (ZipWriter::new(Cursor::new(Vec::new())), ARCHIVE_OVERHEAD));
+
+            if let Err(why) = add_file(writer, &name, &bytes) {
+                error!("Error archiving attachment {}: {}", attachment.url, why);
+                archive.skipped.push(describe(attachment));
+                continue;
+            }
+            *size += entry_size;
+        }
+    }
+
+    if let Some((writer, _)) = part {
+        finish_part(&mut archive, writer);
+    }
+    archive
+}
+
+fn add_file(writer: &mut ZipWriter<Cursor<Vec<u8>>>, name: &str, bytes: &[u8]) -> ZipResult<()> {
+    writer.start_file(
+        name,
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn finish_part(archive: &mut AttachmentArchive, writer: ZipWriter<Cursor<Vec<u8>>>) {
+    match writer.finish() {
+        Ok(cursor) => archive.parts.push(cursor.into_inner()),
+        Err(why) => error!("Error finishing attachment archive: {}", why),
+    }
+}
+
+/// Size of the local file header and central directory entry for a stored file named `name`,
+/// with some headroom for extra fields.
+fn entry_overhead(name: &str) -> u64 {
+    128 + 2 * name.len() as u64
+}
+
+fn describe(attachment: &Attachment) -> String {
+    format!("{} ({})", attachment.filename, attachment.url)
+}
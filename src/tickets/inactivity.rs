@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use log::{error, info, warn};
+use serenity::all::CreateMessage;
+use serenity::client::Context;
+use serenity::model::id::GuildId;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::modmail;
+use crate::tickets::store::{Ticket, TicketStore};
+
+/// Periodically warns about and then closes tickets nobody has written in for a while. Does
+/// nothing if `inactivity_warning` is not configured.
+pub async fn start_inactivity_watch(ctx: Context) {
+    if BotConfig::global_cfg().guild_settings.inactivity_warning <= 0 {
+        return;
+    }
+    info!("Starting ticket inactivity watch...");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            check_idle_tickets(&ctx).await;
+        }
+    });
+}
+
+async fn check_idle_tickets(ctx: &Context) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let warning_after = TimeDelta::seconds(guild_settings.inactivity_warning);
+    let close_after = TimeDelta::seconds(guild_settings.inactivity_close);
+
+    let store = TicketStore::global();
+    let now = Utc::now();
+    let idle_tickets =
+        match store.idle_tickets(GuildId::new(guild_settings.guild_id), now - warning_after) {
+            Ok(tickets) => tickets,
+            Err(why) => {
+                error!("Error looking up idle tickets: {}", why);
+                return;
+            }
+        };
+
+    for ticket in idle_tickets {
+        match ticket.inactivity_warned_at {
+            None => warn_idle_ticket(ctx, &ticket, close_after).await,
+            // Always leave the warning up for at least a minute, even if the thresholds are close
+            Some(warned_at)
+                if ticket.last_activity_at + close_after <= now
+                    && warned_at + TimeDelta::minutes(1) <= now =>
+            {
+                info!("Closing {} for inactivity", ticket.name());
+                let reason = format!(
+                    "Closed automatically after {} of inactivity",
+                    tickets::format_duration(now - ticket.last_activity_at)
+                );
+                let bot_id = ctx.cache.current_user().id;
+                tickets::close_ticket(ctx, ticket.channel_id, bot_id, Some(&reason)).await;
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+async fn warn_idle_ticket(ctx: &Context, ticket: &Ticket, close_after: TimeDelta) {
+    let close_at = (ticket.last_activity_at + close_after)
+        .max(Utc::now() + TimeDelta::minutes(1))
+        .timestamp();
+    let warning = format!(
+        "This ticket has had no activity for {} and will be closed <t:{}:R> unless someone replies.",
+        tickets::format_duration(Utc::now() - ticket.last_activity_at),
+        close_at
+    );
+    // The author of a modmail ticket cannot see its channel, so they are warned by direct message
+    let content = if ticket.modmail {
+        warning.clone()
+    } else {
+        format!("{} {}", ticket.opener_id.mention(), warning)
+    };
+    if let Err(why) = ticket
+        .channel_id
+        .send_message(&ctx.http, CreateMessage::new().content(content))
+        .await
+    {
+        error!("Error warning idle ticket {}: {}", ticket.name(), why);
+        return;
+    }
+    if ticket.modmail {
+        let bot = ctx.cache.current_user().clone();
+        if let Err(why) = modmail::relay_to_opener(ctx, ticket, &bot, &warning, &[]).await {
+            warn!(
+                "Could not warn modmail opener of idle {}: {}",
+                ticket.name(),
+                why
+            );
+        }
+    }
+    if let Err(why) = TicketStore::global().record_inactivity_warning(ticket.id) {
+        error!("Error recording inactivity warning: {}", why);
+    }
+}
@@ -0,0 +1,670 @@
+pub mod archive;
+pub mod inactivity;
+pub mod modmail;
+pub mod retention;
+pub mod sla;
+pub mod store;
+pub mod transcript;
+
+use std::fmt::Write;
+
+use chrono::{TimeDelta, Utc};
+use log::{error, info, warn};
+use serenity::all::{
+    CommandInteraction, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage,
+    EditChannel, EditMessage, EditThread, EmbedField, GuildChannel, PermissionOverwriteType,
+    Permissions,
+};
+use serenity::client::Context;
+use serenity::futures::StreamExt;
+use serenity::http::HttpError;
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId, RoleId, UserId};
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::{BotConfig, TicketTopic};
+use crate::commands;
+use crate::tickets::store::{Ticket, TicketStore};
+
+/// Discord's error code for a channel that does not exist (any more).
+const UNKNOWN_CHANNEL: isize = 10003;
+
+/// The most characters Discord allows in a message.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// Whether a member with `roles` is on the support team.
+pub fn is_support_team(roles: &[RoleId]) -> bool {
+    roles.contains(&RoleId::new(
+        BotConfig::global_cfg().guild_settings.support_team_role,
+    ))
+}
+
+/// The roles that staff a ticket of `topic`: the support team and the topic's own staff role.
+pub fn staff_roles(topic: &TicketTopic) -> Vec<RoleId> {
+    let mut roles = vec![RoleId::new(
+        BotConfig::global_cfg().guild_settings.support_team_role,
+    )];
+    if !roles.contains(&RoleId::new(topic.staff_role)) {
+        roles.push(RoleId::new(topic.staff_role));
+    }
+    roles
+}
+
+/// Whether a member with `roles` may act as staff on `ticket`.
+pub fn is_ticket_staff(roles: &[RoleId], ticket: &Ticket) -> bool {
+    staff_roles(ticket.topic_settings())
+        .iter()
+        .any(|role| roles.contains(role))
+}
+
+/// The open ticket in the channel `command` was run in, if the user is on its staff, or else the
+/// response to send.
+pub fn staff_ticket(command: &CommandInteraction) -> Result<Ticket, String> {
+    let ticket = match TicketStore::global().open_ticket_by_channel(command.channel_id) {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => return Err("This command can only be run in a ticket channel!".to_string()),
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            return Err("Error looking up ticket".to_string());
+        }
+    };
+
+    if !command
+        .member
+        .as_ref()
+        .is_some_and(|m| is_ticket_staff(&m.roles, &ticket))
+    {
+        return Err("This command is restricted to the Shalom Support team!".to_string());
+    }
+    Ok(ticket)
+}
+
+/// Whether `name` looks like the channel name of a ticket of any topic, e.g. `report-12`.
+pub fn is_ticket_channel_name(name: &str) -> bool {
+    ticket_channel_number(name).is_some()
+}
+
+/// The ticket number in a ticket channel name of any topic, e.g. 12 for `report-12`.
+pub fn ticket_channel_number(name: &str) -> Option<u64> {
+    BotConfig::global_cfg()
+        .guild_settings
+        .ticket_topics
+        .iter()
+        .find_map(|topic| {
+            name.strip_prefix(&topic.channel_prefix)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .and_then(|number| number.parse::<u64>().ok())
+        })
+}
+
+/// Whether `channel` is exclusively claimed, which is recognisable by the staff roles of `ticket`
+/// being unable to send messages in it.
+pub fn is_exclusively_claimed(channel: &GuildChannel, ticket: &Ticket) -> bool {
+    let staff_roles = staff_roles(ticket.topic_settings());
+    channel.permission_overwrites.iter().any(|overwrite| {
+        staff_roles
+            .iter()
+            .any(|role| overwrite.kind == PermissionOverwriteType::Role(*role))
+            && overwrite.deny.contains(Permissions::SEND_MESSAGES)
+    })
+}
+
+/// Updates the "Claimed By" field of the ticket's welcome embed to match the stored claimer.
+pub async fn refresh_welcome_embed(
+    ctx: &Context,
+    channel_id: ChannelId,
+    ticket: &Ticket,
+) -> serenity::Result<()> {
+    let Some(message_id) = ticket.welcome_message_id else {
+        return Ok(());
+    };
+    let message = channel_id.message(&ctx.http, message_id).await?;
+    let Some(mut embed) = message.embeds.into_iter().next() else {
+        return Ok(());
+    };
+
+    embed.fields.retain(|field| field.name != "Claimed By");
+    if let Some(claimer_id) = ticket.claimer_id {
+        embed.fields.push(EmbedField::new(
+            "Claimed By",
+            claimer_id.mention().to_string(),
+            true,
+        ));
+    }
+
+    channel_id
+        .edit_message(
+            &ctx.http,
+            message_id,
+            EditMessage::new().embed(CreateEmbed::from(embed)),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Logs the contents of a ticket channel to the ticket log channel, records the close in the
+/// ticket store, sends the opener a copy of the transcript and deletes or archives the channel.
+pub async fn close_ticket(
+    ctx: &Context,
+    channel_id: ChannelId,
+    closer_id: UserId,
+    reason: Option<&str>,
+) {
+    let mut message_vec = Vec::new();
+    let mut messages = channel_id.messages_iter(&ctx.http).boxed();
+    while let Some(message_result) = messages.next().await {
+        match message_result {
+            Ok(message) => message_vec.push(message),
+            Err(why) => error!("Error retrieving ticket messages: {}", why),
+        }
+    }
+    message_vec.reverse();
+
+    let store = TicketStore::global();
+    let ticket = match store.open_ticket_by_channel(channel_id) {
+        Ok(Some(ticket)) => match store.close_ticket(ticket.id, closer_id, reason) {
+            Ok(Some(ticket)) => Some(ticket),
+            Ok(None) => {
+                info!("{} is already being closed", ticket.name());
+                return;
+            }
+            Err(why) => {
+                error!("Error recording ticket close: {}", why);
+                abandon_close(ctx, channel_id, None).await;
+                return;
+            }
+        },
+        // The stored ticket was closed by a concurrent close after the caller checked the channel
+        Ok(None)
+            if store
+                .archived_ticket_by_channel(channel_id)
+                .is_ok_and(|archived| archived.is_some()) =>
+        {
+            info!("Ticket channel {} is already being closed", channel_id);
+            return;
+        }
+        Ok(None) => {
+            warn!(
+                "Closing ticket channel {} with no stored record",
+                channel_id
+            );
+            None
+        }
+        Err(why) => {
+            error!("Error looking up ticket: {}", why);
+            None
+        }
+    };
+
+    let answers = match &ticket {
+        Some(ticket) => store.answers(ticket.id).unwrap_or_else(|why| {
+            error!("Error looking up ticket intake answers: {}", why);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let notes = match &ticket {
+        Some(ticket) => store.notes(ticket.id).unwrap_or_else(|why| {
+            error!("Error looking up ticket notes: {}", why);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let title = ticket
+        .as_ref()
+        .map_or("Ticket".to_string(), |ticket| ticket.name());
+    let html_transcript = transcript::render_html(&title, &message_vec);
+
+    let text_transcript = transcript::render_text(&message_vec);
+
+    let log_channel = ChannelId::new(BotConfig::global_cfg().guild_settings.ticket_log_channel);
+
+    // Attachments go first, so a failed upload leaves no log behind to be duplicated on a retry
+    let archive = archive::archive_attachments(
+        &message_vec,
+        BotConfig::global_cfg().guild_settings.log_upload_limit,
+    )
+    .await;
+    let part_count = archive.parts.len();
+    let mut part_messages = Vec::with_capacity(part_count);
+    for (i, part) in archive.parts.into_iter().enumerate() {
+        let filename = if part_count == 1 {
+            "attachments.zip".to_string()
+        } else {
+            format!("attachments-part{}.zip", i + 1)
+        };
+        match log_channel
+            .send_message(
+                &ctx.http,
+                CreateMessage::new()
+                    .content(format!(
+                        "Attachments for {} ({}/{})",
+                        title,
+                        i + 1,
+                        part_count
+                    ))
+                    .add_file(CreateAttachment::bytes(part, filename)),
+            )
+            .await
+        {
+            Ok(part_message) => part_messages.push(part_message.id),
+            Err(why) => {
+                error!(
+                    "Error uploading ticket attachments, keeping channel: {}",
+                    why
+                );
+                delete_log_messages(ctx, log_channel, &part_messages).await;
+                abandon_close(ctx, channel_id, ticket.as_ref()).await;
+                return;
+            }
+        }
+    }
+
+    let log_message = log_channel
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .embed(log_embed(
+                    ticket.as_ref(),
+                    &answers,
+                    &message_vec,
+                    notes.len(),
+                ))
+                .add_file(CreateAttachment::bytes(text_transcript.as_str(), "log.txt"))
+                .add_file(CreateAttachment::bytes(
+                    html_transcript.as_str(),
+                    "transcript.html",
+                ))
+                .add_file(CreateAttachment::bytes(
+                    transcript::render_staff_html(&title, &message_vec, &notes),
+                    "staff-transcript.html",
+                )),
+        )
+        .await;
+    let log_message = match log_message {
+        Ok(log_message) => log_message,
+        Err(why) => {
+            error!("Error posting ticket log, keeping channel: {}", why);
+            delete_log_messages(ctx, log_channel, &part_messages).await;
+            abandon_close(ctx, channel_id, ticket.as_ref()).await;
+            return;
+        }
+    };
+
+    if let Some(ticket) = &ticket {
+        // Intake answers are searchable along with the conversation
+        let mut searchable = text_transcript;
+        for (question, answer) in &answers {
+            let _ = write!(searchable, "\n{}: {}", question, answer);
+        }
+        if let Err(why) =
+            store.index_transcript(ticket.id, log_channel, log_message.id, &searchable)
+        {
+            error!("Error indexing ticket transcript: {}", why);
+        }
+    }
+
+    let skipped_header = format!("Attachments for {} that could not be archived:", title);
+    for content in split_message(&skipped_header, &archive.skipped) {
+        if let Err(why) = log_channel
+            .send_message(&ctx.http, CreateMessage::new().content(content))
+            .await
+        {
+            error!("Error listing unarchived ticket attachments: {}", why);
+        }
+    }
+
+    if let Some(ticket) = &ticket {
+        if let Err(why) = send_transcript_to_opener(ctx, ticket, &html_transcript).await {
+            warn!("Could not DM transcript to ticket opener: {}", why);
+            let _ = log_channel
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new()
+                        .content(format!(
+                            "Could not send {} a copy of {}, they may have DMs disabled.",
+                            ticket.opener_id.mention(),
+                            title
+                        ))
+                        .allowed_mentions(CreateAllowedMentions::new()),
+                )
+                .await;
+        }
+    }
+
+    match (
+        &ticket,
+        BotConfig::global_cfg().guild_settings.archive_category,
+    ) {
+        (Some(ticket), Some(archive_category)) => {
+            archive_ticket_channel(ctx, ticket, ChannelId::new(archive_category)).await
+        }
+        _ => delete_ticket_channel(ctx, channel_id, ticket.as_ref()).await,
+    }
+}
+
+/// Leaves a ticket open after its log could not be posted, so nothing is lost and it can be
+/// closed again.
+async fn abandon_close(ctx: &Context, channel_id: ChannelId, ticket: Option<&Ticket>) {
+    if let Some(ticket) = ticket {
+        if let Err(why) = TicketStore::global().undo_close(ticket.id) {
+            error!("Error reverting ticket close: {}", why);
+        }
+    }
+    let _ = channel_id
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .content("This ticket could not be logged, so it has been kept open. Please try closing it again later."),
+        )
+        .await;
+}
+
+/// Removes the messages already posted to the log channel for a close that is abandoned.
+async fn delete_log_messages(ctx: &Context, log_channel: ChannelId, messages: &[MessageId]) {
+    for message in messages {
+        if let Err(why) = log_channel.delete_message(&ctx.http, message).await {
+            error!("Error removing abandoned ticket log message: {}", why);
+        }
+    }
+}
+
+/// Splits `lines` under `header` into messages that fit Discord's message limit, cutting single
+/// lines that would not fit on their own. Returns no messages if there are no lines.
+fn split_message(header: &str, lines: &[String]) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = header.to_string();
+    for line in lines {
+        let line: String = line.chars().take(MESSAGE_LIMIT - 1).collect();
+        if current.chars().count() + 1 + line.chars().count() > MESSAGE_LIMIT {
+            messages.push(std::mem::take(&mut current));
+            current = line;
+        } else {
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&line);
+        }
+    }
+    if !lines.is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// Deletes a ticket's channel, recording that in the ticket store if the ticket is stored.
+pub async fn delete_ticket_channel(ctx: &Context, channel_id: ChannelId, ticket: Option<&Ticket>) {
+    match channel_id.delete(&ctx.http).await {
+        Ok(_) => {
+            info!("Ticket Channel Deleted")
+        }
+        // Already deleted by hand, so there is nothing left to retry
+        Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(response)))
+            if response.error.code == UNKNOWN_CHANNEL =>
+        {
+            info!("Ticket Channel {} Already Deleted", channel_id)
+        }
+        Err(error) => {
+            error!("Error Deleting Ticket Channel: {}", error);
+            return;
+        }
+    }
+    if let Some(ticket) = ticket {
+        if let Err(why) = TicketStore::global().set_channel_deleted(ticket.id) {
+            error!("Error recording ticket channel deletion: {}", why);
+        }
+    }
+}
+
+/// Keeps a closed ticket's channel for `/reopen`: everyone but staff loses access to it and it is
+/// moved to the archive category until the retention job deletes it. Threads are archived and
+/// locked in place instead.
+async fn archive_ticket_channel(ctx: &Context, ticket: &Ticket, archive_category: ChannelId) {
+    if ticket.thread {
+        if let Err(why) = ticket
+            .channel_id
+            .remove_thread_member(&ctx.http, ticket.opener_id)
+            .await
+        {
+            error!("Error removing opener from archived ticket: {}", why);
+        }
+    } else {
+        match ticket.channel_id.to_channel(&ctx.http).await {
+            Ok(channel) => {
+                let members = channel
+                    .guild()
+                    .map(|channel| channel.permission_overwrites)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|overwrite| {
+                        matches!(overwrite.kind, PermissionOverwriteType::Member(_))
+                    });
+                for overwrite in members {
+                    if let Err(why) = ticket
+                        .channel_id
+                        .delete_permission(&ctx.http, overwrite.kind)
+                        .await
+                    {
+                        error!("Error removing member from archived ticket: {}", why);
+                    }
+                }
+            }
+            Err(why) => error!("Error fetching ticket channel: {}", why),
+        }
+
+        if let Err(why) = ticket
+            .channel_id
+            .edit(&ctx.http, EditChannel::new().category(archive_category))
+            .await
+        {
+            error!("Error moving ticket channel to archive: {}", why);
+        }
+    }
+
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let closed_at = ticket.closed_at.unwrap_or_else(Utc::now);
+    let _ = ticket
+        .channel_id
+        .send_message(
+            &ctx.http,
+            CreateMessage::new().content(format!(
+                "This ticket has been closed and archived. Staff can /reopen it until <t:{}:f>, it will be deleted <t:{}:R>.",
+                (closed_at + TimeDelta::seconds(guild_settings.reopen_window)).timestamp(),
+                (closed_at + TimeDelta::seconds(guild_settings.archive_retention)).timestamp()
+            )),
+        )
+        .await;
+
+    // Archiving has to come last, as sending a message unarchives the thread again
+    if ticket.thread {
+        if let Err(why) = ticket
+            .channel_id
+            .edit_thread(&ctx.http, EditThread::new().locked(true).archived(true))
+            .await
+        {
+            error!("Error archiving ticket thread: {}", why);
+        }
+    }
+    info!("Ticket Channel Archived");
+}
+
+async fn send_transcript_to_opener(
+    ctx: &Context,
+    ticket: &Ticket,
+    html_transcript: &str,
+) -> serenity::Result<()> {
+    let mut embed = CreateEmbed::new()
+        .title("Ticket Closed")
+        .description("Thank you for contacting Shalom Support. A copy of your ticket is attached.")
+        .color(Color::from(BotConfig::global_cfg().embed_color))
+        .field("Ticket ID", ticket.name(), true);
+    if let Some(closed_at) = ticket.closed_at {
+        embed = embed.field(
+            "Duration",
+            format_duration(closed_at - ticket.opened_at),
+            true,
+        );
+    }
+    embed = embed.field(
+        "Reason",
+        ticket.close_reason.as_deref().unwrap_or("No reason given"),
+        false,
+    );
+
+    ticket
+        .opener_id
+        .create_dm_channel(&ctx.http)
+        .await?
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .embed(embed)
+                .add_file(CreateAttachment::bytes(
+                    html_transcript,
+                    format!("{}.html", ticket.name()),
+                ))
+                .content("How did we do? Rate your support below.")
+                .components(vec![commands::ratings::rating_buttons(ticket.id)]),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Parses a duration such as `30m`, `12h`, `7d` or `2w`.
+pub fn parse_duration(text: &str) -> Option<TimeDelta> {
+    let text = text.trim();
+    let unit = text.chars().last()?;
+    let amount: i64 = text[..text.len() - unit.len_utf8()].trim().parse().ok()?;
+    match unit.to_ascii_lowercase() {
+        'm' => TimeDelta::try_minutes(amount),
+        'h' => TimeDelta::try_hours(amount),
+        'd' => TimeDelta::try_days(amount),
+        'w' => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+    .filter(|duration| *duration > TimeDelta::zero())
+}
+
+/// Formats a duration as e.g. `2d 3h 15m`, omitting leading zero units.
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// The embed posted in the log channel when a ticket is closed, summarising it so staff don't
+/// need to download the transcript. `ticket` is `None` for channels with no stored record.
+fn log_embed(
+    ticket: Option<&Ticket>,
+    answers: &[(String, String)],
+    messages: &[Message],
+    note_count: usize,
+) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title("Ticket Logged")
+        .color(Color::from(BotConfig::global_cfg().embed_color));
+
+    if let Some(ticket) = ticket {
+        embed = embed
+            .field("Ticket ID", ticket.name(), true)
+            .field("Topic", &ticket.topic_settings().label, true)
+            .field("Opened By", ticket.opener_id.mention().to_string(), true)
+            .field(
+                "Claimed By",
+                ticket
+                    .claimer_id
+                    .map_or("Unclaimed".to_string(), |claimer_id| {
+                        claimer_id.mention().to_string()
+                    }),
+                true,
+            )
+            .field(
+                "Closed By",
+                ticket.closer_id.map_or("Unknown".to_string(), |closer_id| {
+                    closer_id.mention().to_string()
+                }),
+                true,
+            )
+            .field(
+                "Reason",
+                ticket.close_reason.as_deref().unwrap_or("No reason given"),
+                false,
+            )
+            .field(
+                "Opened",
+                format!("<t:{}:f>", ticket.opened_at.timestamp()),
+                true,
+            );
+        if let Some(closed_at) = ticket.closed_at {
+            embed = embed
+                .field("Closed", format!("<t:{}:f>", closed_at.timestamp()), true)
+                .field(
+                    "Duration",
+                    format_duration(closed_at - ticket.opened_at),
+                    true,
+                );
+        }
+        embed = embed.field(
+            "First Staff Reply",
+            ticket.first_staff_reply_at.map_or(
+                "No staff reply".to_string(),
+                |first_staff_reply_at| {
+                    format!(
+                        "<t:{}:f> (after {})",
+                        first_staff_reply_at.timestamp(),
+                        format_duration(first_staff_reply_at - ticket.opened_at)
+                    )
+                },
+            ),
+            true,
+        );
+        if let Some(last_staff_reply_at) = ticket.last_staff_reply_at {
+            embed = embed.field(
+                "Last Staff Reply",
+                format!("<t:{}:f>", last_staff_reply_at.timestamp()),
+                true,
+            );
+        }
+    }
+
+    // Everyone who wrote in the ticket, in order of their first message, with their message count
+    let mut participants: Vec<(UserId, usize)> = Vec::new();
+    for message in messages.iter().filter(|message| !message.author.bot) {
+        match participants
+            .iter_mut()
+            .find(|(user_id, _)| *user_id == message.author.id)
+        {
+            Some((_, count)) => *count += 1,
+            None => participants.push((message.author.id, 1)),
+        }
+    }
+    let mut participant_list = String::new();
+    for (user_id, count) in &participants {
+        let line = format!("{} ({})\n", user_id.mention(), count);
+        if participant_list.len() + line.len() > 1000 {
+            participant_list += "…";
+            break;
+        }
+        participant_list += &line;
+    }
+    if participant_list.is_empty() {
+        participant_list = "Nobody".to_string();
+    }
+    embed = embed
+        .field("Messages", messages.len().to_string(), true)
+        .field("Staff Notes", note_count.to_string(), true)
+        .field("Participants", participant_list, false);
+
+    for (question, answer) in answers {
+        let answer: String = answer.chars().take(200).collect();
+        embed = embed.field(question, answer, false);
+    }
+    embed
+}
@@ -0,0 +1,189 @@
+use log::{error, warn};
+use serenity::all::{
+    Attachment, CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage,
+    ReactionType, User,
+};
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::GuildId;
+use serenity::model::Color;
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::commands::ticket;
+use crate::tickets::store::{Ticket, TicketStore};
+
+/// Relays a direct message to the bot into the author's modmail ticket, opening one if they have
+/// none.
+pub async fn handle_direct_message(ctx: &Context, message: &Message) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let guild_id = GuildId::new(guild_settings.guild_id);
+    let store = TicketStore::global();
+
+    let ticket = match store.open_modmail_ticket(guild_id, message.author.id) {
+        Ok(Some(ticket)) => ticket,
+        Ok(None) => {
+            let reply = match ticket::refusal_notice(message.author.id) {
+                Some(notice) => notice,
+                None => {
+                    let first_message = if message.content.trim().is_empty() {
+                        "(attachments only)".to_string()
+                    } else {
+                        message.content.chars().take(1000).collect()
+                    };
+                    ticket::open(
+                        ctx,
+                        message.author.id,
+                        guild_settings.default_topic(),
+                        &[("Message".to_string(), first_message)],
+                        true,
+                    )
+                    .await;
+                    match store.open_modmail_ticket(guild_id, message.author.id) {
+                        Ok(Some(ticket)) => {
+                            relay_to_ticket(ctx, &ticket, message).await;
+                            return;
+                        }
+                        Ok(None) => "Error creating ticket".to_string(),
+                        Err(why) => {
+                            error!("Error looking up new modmail ticket: {}", why);
+                            "Error creating ticket".to_string()
+                        }
+                    }
+                }
+            };
+            let _ = message.reply(&ctx.http, reply).await;
+            return;
+        }
+        Err(why) => {
+            error!("Error looking up modmail ticket: {}", why);
+            return;
+        }
+    };
+
+    relay_to_ticket(ctx, &ticket, message).await;
+}
+
+/// Posts a direct message from the ticket author into the ticket channel.
+async fn relay_to_ticket(ctx: &Context, ticket: &Ticket, message: &Message) {
+    let relay = relay_message(
+        &message.author,
+        &message.content,
+        &message.attachments,
+        "Sent by direct message",
+    );
+    match ticket
+        .channel_id
+        .send_message(
+            &ctx.http,
+            with_attachments(ctx, relay, &message.attachments).await,
+        )
+        .await
+    {
+        Ok(_) => {
+            let store = TicketStore::global();
+            if let Err(why) = store.record_activity(ticket.id) {
+                error!("Error recording ticket activity: {}", why);
+            }
+            if let Err(why) = store.record_author_message(ticket.id) {
+                error!("Error recording ticket author message: {}", why);
+            }
+            let _ = message
+                .react(&ctx.http, ReactionType::Unicode("📨".to_string()))
+                .await;
+        }
+        Err(why) => {
+            error!(
+                "Error relaying direct message to {}: {}",
+                ticket.name(),
+                why
+            );
+            let _ = message
+                .reply(
+                    &ctx.http,
+                    "Your message could not be delivered to Shalom Support, please try again.",
+                )
+                .await;
+        }
+    }
+}
+
+/// Sends a staff member's message in a modmail ticket to the ticket author's direct messages.
+pub async fn relay_to_opener(
+    ctx: &Context,
+    ticket: &Ticket,
+    staff: &User,
+    content: &str,
+    attachments: &[Attachment],
+) -> serenity::Result<()> {
+    let relay = relay_message(staff, content, attachments, "Shalom Support Team ™");
+    ticket
+        .opener_id
+        .create_dm_channel(&ctx.http)
+        .await?
+        .send_message(&ctx.http, with_attachments(ctx, relay, attachments).await)
+        .await?;
+    Ok(())
+}
+
+/// An embed showing `content` as written by `author`.
+fn relay_message(
+    author: &User,
+    content: &str,
+    attachments: &[Attachment],
+    footer: &str,
+) -> CreateMessage {
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(author.display_name()).icon_url(author.face()))
+        .color(Color::from(BotConfig::global_cfg().embed_color))
+        .footer(CreateEmbedFooter::new(footer));
+    if !content.is_empty() {
+        embed = embed.description(content);
+    }
+    if !attachments.is_empty() {
+        let files: Vec<String> = attachments
+            .iter()
+            .map(|attachment| attachment.filename.clone())
+            .collect();
+        embed = embed.field("Attachments", files.join("\n"), false);
+    }
+    CreateMessage::new().embed(embed)
+}
+
+/// Re-uploads `attachments` with the relayed message, linking any too large to re-upload.
+async fn with_attachments(
+    ctx: &Context,
+    mut relay: CreateMessage,
+    attachments: &[Attachment],
+) -> CreateMessage {
+    let upload_limit = BotConfig::global_cfg().guild_settings.log_upload_limit;
+    let mut links = Vec::new();
+    for attachment in attachments {
+        if u64::from(attachment.size) > upload_limit {
+            links.push(attachment.url.clone());
+            continue;
+        }
+        match CreateAttachment::url(&ctx.http, &attachment.url).await {
+            Ok(file) => relay = relay.add_file(file),
+            Err(why) => {
+                warn!(
+                    "Could not download {} to relay: {}",
+                    attachment.filename, why
+                );
+                links.push(attachment.url.clone());
+            }
+        }
+    }
+    if !links.is_empty() {
+        relay = relay.content(links.join("\n"));
+    }
+    relay
+}
+
+/// The note posted in the ticket when a relay to the ticket author fails.
+pub fn undelivered_notice(ticket: &Ticket) -> String {
+    format!(
+        "Could not deliver that message to {}, they may have DMs disabled.",
+        ticket.opener_id.mention()
+    )
+}
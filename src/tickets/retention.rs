@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use log::{error, info};
+use serenity::client::Context;
+use serenity::model::id::GuildId;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::TicketStore;
+
+/// Periodically deletes archived ticket channels once they are older than `archive_retention`.
+/// Does nothing if closed tickets are not archived.
+pub async fn start_retention_watch(ctx: Context) {
+    if BotConfig::global_cfg()
+        .guild_settings
+        .archive_category
+        .is_none()
+    {
+        return;
+    }
+    info!("Starting ticket archive retention watch...");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(600));
+        loop {
+            interval.tick().await;
+            delete_expired_archives(&ctx).await;
+        }
+    });
+}
+
+async fn delete_expired_archives(ctx: &Context) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let expired = match TicketStore::global().expired_archives(
+        GuildId::new(guild_settings.guild_id),
+        Utc::now() - TimeDelta::seconds(guild_settings.archive_retention),
+    ) {
+        Ok(tickets) => tickets,
+        Err(why) => {
+            error!("Error looking up expired ticket archives: {}", why);
+            return;
+        }
+    };
+
+    for ticket in expired {
+        info!("Deleting archived {}", ticket.name());
+        tickets::delete_ticket_channel(ctx, ticket.channel_id, Some(&ticket)).await;
+    }
+}
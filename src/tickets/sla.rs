@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use log::{error, info};
+use serenity::all::{CreateAllowedMentions, CreateMessage};
+use serenity::client::Context;
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use serenity::prelude::Mentionable;
+
+use crate::botconfig::BotConfig;
+use crate::tickets;
+use crate::tickets::store::{Ticket, TicketStore};
+
+/// Periodically alerts staff about tickets that have waited longer than the configured response
+/// times for a staff reply. Does nothing if `sla_alert_channel` is not configured.
+pub async fn start_sla_watch(ctx: Context) {
+    if BotConfig::global_cfg().guild_settings.sla_alert_channel == 0 {
+        return;
+    }
+    info!("Starting ticket SLA watch...");
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            check_waiting_tickets(&ctx).await;
+        }
+    });
+}
+
+async fn check_waiting_tickets(ctx: &Context) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let now = Utc::now();
+    let waiting_tickets = match TicketStore::global().unalerted_waiting_tickets(
+        GuildId::new(guild_settings.guild_id),
+        now - TimeDelta::seconds(guild_settings.sla_first_response),
+        now - TimeDelta::seconds(guild_settings.sla_follow_up),
+    ) {
+        Ok(tickets) => tickets,
+        Err(why) => {
+            error!("Error looking up tickets waiting for staff: {}", why);
+            return;
+        }
+    };
+
+    for ticket in waiting_tickets {
+        alert_staff(ctx, &ticket).await;
+    }
+}
+
+async fn alert_staff(ctx: &Context, ticket: &Ticket) {
+    let guild_settings = &BotConfig::global_cfg().guild_settings;
+    let role = RoleId::new(
+        guild_settings
+            .sla_escalation_role
+            .unwrap_or(ticket.topic_settings().staff_role),
+    );
+    let now = Utc::now();
+    let waiting = match (ticket.last_staff_reply_at, ticket.awaiting_staff_since) {
+        (Some(_), Some(awaiting_staff_since)) => format!(
+            "has had no staff reply for {} since its author wrote again",
+            tickets::format_duration(now - awaiting_staff_since)
+        ),
+        _ => format!(
+            "has been waiting {} for a first staff reply",
+            tickets::format_duration(now - ticket.opened_at)
+        ),
+    };
+    let alert = format!(
+        "{} {} ({}) {}.",
+        role.mention(),
+        ticket.channel_id.mention(),
+        ticket.name(),
+        waiting
+    );
+
+    if let Err(why) = ChannelId::new(guild_settings.sla_alert_channel)
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .content(alert)
+                .allowed_mentions(CreateAllowedMentions::new().roles(vec![role])),
+        )
+        .await
+    {
+        error!("Error alerting staff about {}: {}", ticket.name(), why);
+        return;
+    }
+    if let Err(why) = TicketStore::global().record_sla_alert(ticket.id) {
+        error!("Error recording SLA alert: {}", why);
+    }
+}
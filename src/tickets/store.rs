@@ -0,0 +1,1293 @@
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+use log::info;
+use once_cell::sync::OnceCell;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+
+use crate::botconfig::{BotConfig, TicketTopic};
+
+/// Schema migrations, applied in order. The index of the last applied migration is tracked with
+/// sqlite's `user_version` pragma, so new migrations must only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE tickets (
+        id                   INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id             INTEGER NOT NULL,
+        number               INTEGER NOT NULL,
+        channel_id           INTEGER NOT NULL,
+        opener_id            INTEGER NOT NULL,
+        opened_at            TEXT    NOT NULL,
+        first_staff_reply_at TEXT,
+        claimer_id           INTEGER,
+        closer_id            INTEGER,
+        close_reason         TEXT,
+        closed_at            TEXT
+    );
+    CREATE INDEX tickets_channel_id ON tickets (channel_id);",
+    "CREATE TABLE ticket_counters (
+        guild_id    INTEGER PRIMARY KEY,
+        last_number INTEGER NOT NULL
+    );
+    INSERT INTO ticket_counters (guild_id, last_number)
+        SELECT guild_id, MAX(number) FROM tickets GROUP BY guild_id;
+    CREATE UNIQUE INDEX tickets_guild_id_number ON tickets (guild_id, number);",
+    "CREATE TABLE ticket_events (
+        id        INTEGER PRIMARY KEY AUTOINCREMENT,
+        ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+        kind      TEXT    NOT NULL,
+        actor_id  INTEGER NOT NULL,
+        detail    TEXT,
+        at        TEXT    NOT NULL
+    );
+    CREATE INDEX ticket_events_ticket_id ON ticket_events (ticket_id);",
+    "ALTER TABLE tickets ADD COLUMN welcome_message_id INTEGER;",
+    "ALTER TABLE tickets ADD COLUMN topic TEXT NOT NULL DEFAULT 'general';",
+    "CREATE TABLE ticket_answers (
+        ticket_id INTEGER NOT NULL REFERENCES tickets (id),
+        position  INTEGER NOT NULL,
+        question  TEXT    NOT NULL,
+        answer    TEXT    NOT NULL,
+        PRIMARY KEY (ticket_id, position)
+    );",
+    "CREATE TABLE ticket_bans (
+        guild_id   INTEGER NOT NULL,
+        user_id    INTEGER NOT NULL,
+        banned_by  INTEGER NOT NULL,
+        reason     TEXT,
+        banned_at  TEXT    NOT NULL,
+        expires_at TEXT,
+        PRIMARY KEY (guild_id, user_id)
+    );",
+    "ALTER TABLE tickets ADD COLUMN last_activity_at TEXT;
+    UPDATE tickets SET last_activity_at = COALESCE(first_staff_reply_at, opened_at);
+    ALTER TABLE tickets ADD COLUMN inactivity_warned_at TEXT;
+    ALTER TABLE tickets ADD COLUMN on_hold INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE tickets ADD COLUMN last_staff_reply_at TEXT;
+    UPDATE tickets SET last_staff_reply_at = first_staff_reply_at;
+    ALTER TABLE tickets ADD COLUMN sla_alerted_at TEXT;",
+    "ALTER TABLE tickets ADD COLUMN channel_deleted_at TEXT;
+    UPDATE tickets SET channel_deleted_at = closed_at;",
+    "CREATE INDEX ticket_events_kind_at ON ticket_events (kind, at);",
+    "CREATE TABLE ticket_ratings (
+        ticket_id INTEGER PRIMARY KEY REFERENCES tickets (id),
+        score     INTEGER NOT NULL,
+        feedback  TEXT,
+        rated_at  TEXT    NOT NULL
+    );",
+    "CREATE TABLE ticket_notes (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        ticket_id   INTEGER NOT NULL REFERENCES tickets (id),
+        author_id   INTEGER NOT NULL,
+        author_name TEXT    NOT NULL,
+        body        TEXT    NOT NULL,
+        created_at  TEXT    NOT NULL
+    );
+    CREATE INDEX ticket_notes_ticket ON ticket_notes (ticket_id);",
+    "ALTER TABLE tickets ADD COLUMN thread INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE tickets ADD COLUMN modmail INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE ticket_snippets (
+        guild_id   INTEGER NOT NULL,
+        name       TEXT    NOT NULL,
+        body       TEXT    NOT NULL,
+        updated_by INTEGER NOT NULL,
+        updated_at TEXT    NOT NULL,
+        PRIMARY KEY (guild_id, name)
+    );",
+    "CREATE VIRTUAL TABLE ticket_transcripts USING fts5 (transcript);
+    ALTER TABLE tickets ADD COLUMN log_channel_id INTEGER;
+    ALTER TABLE tickets ADD COLUMN log_message_id INTEGER;",
+    "ALTER TABLE tickets ADD COLUMN awaiting_staff_since TEXT;
+    UPDATE tickets SET awaiting_staff_since = last_activity_at
+        WHERE closed_at IS NULL AND last_activity_at > last_staff_reply_at;",
+];
+
+/// Something that happened to a ticket, recorded in its history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Opened,
+    CloseRequested,
+    CloseConfirmed,
+    CloseCancelled,
+    Closed,
+    Reopened,
+    Claimed,
+    Unclaimed,
+    StaffReplied,
+    MemberAdded,
+    MemberRemoved,
+    Held,
+    Unheld,
+}
+
+impl EventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Opened => "opened",
+            EventKind::CloseRequested => "close_requested",
+            EventKind::CloseConfirmed => "close_confirmed",
+            EventKind::CloseCancelled => "close_cancelled",
+            EventKind::Closed => "closed",
+            EventKind::Reopened => "reopened",
+            EventKind::Claimed => "claimed",
+            EventKind::Unclaimed => "unclaimed",
+            EventKind::StaffReplied => "staff_replied",
+            EventKind::MemberAdded => "member_added",
+            EventKind::MemberRemoved => "member_removed",
+            EventKind::Held => "held",
+            EventKind::Unheld => "unheld",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub(crate) id: i64,
+    pub(crate) number: u64,
+    pub(crate) topic: String,
+    pub(crate) channel_id: ChannelId,
+    /// Whether the ticket is a private thread rather than a channel
+    pub(crate) thread: bool,
+    /// Whether the opener takes part through direct messages relayed by the bot
+    pub(crate) modmail: bool,
+    pub(crate) opener_id: UserId,
+    pub(crate) opened_at: DateTime<Utc>,
+    pub(crate) first_staff_reply_at: Option<DateTime<Utc>>,
+    pub(crate) last_staff_reply_at: Option<DateTime<Utc>>,
+    pub(crate) claimer_id: Option<UserId>,
+    pub(crate) closer_id: Option<UserId>,
+    pub(crate) close_reason: Option<String>,
+    pub(crate) closed_at: Option<DateTime<Utc>>,
+    pub(crate) welcome_message_id: Option<MessageId>,
+    /// When a message was last sent in the ticket by someone other than the bot
+    pub(crate) last_activity_at: DateTime<Utc>,
+    /// When the ticket was warned it is about to be closed for inactivity, if it has been since
+    /// the last activity
+    pub(crate) inactivity_warned_at: Option<DateTime<Utc>>,
+    /// Whether staff have exempted the ticket from being closed for inactivity
+    pub(crate) on_hold: bool,
+    /// When the author's oldest message no staff member has replied to yet was sent
+    pub(crate) awaiting_staff_since: Option<DateTime<Utc>>,
+}
+
+impl Ticket {
+    const COLUMNS: &'static str =
+        "id, number, topic, channel_id, thread, modmail, opener_id, opened_at, \
+        first_staff_reply_at, claimer_id, closer_id, close_reason, closed_at, welcome_message_id, \
+        last_activity_at, inactivity_warned_at, on_hold, last_staff_reply_at, awaiting_staff_since";
+
+    fn from_row(row: &Row) -> rusqlite::Result<Ticket> {
+        Ok(Ticket {
+            id: row.get("id")?,
+            number: row.get("number")?,
+            topic: row.get("topic")?,
+            channel_id: ChannelId::new(row.get("channel_id")?),
+            thread: row.get("thread")?,
+            modmail: row.get("modmail")?,
+            opener_id: UserId::new(row.get("opener_id")?),
+            opened_at: row.get("opened_at")?,
+            first_staff_reply_at: row.get("first_staff_reply_at")?,
+            claimer_id: row.get::<_, Option<u64>>("claimer_id")?.map(UserId::new),
+            closer_id: row.get::<_, Option<u64>>("closer_id")?.map(UserId::new),
+            close_reason: row.get("close_reason")?,
+            closed_at: row.get("closed_at")?,
+            welcome_message_id: row
+                .get::<_, Option<u64>>("welcome_message_id")?
+                .map(MessageId::new),
+            last_activity_at: row.get("last_activity_at")?,
+            inactivity_warned_at: row.get("inactivity_warned_at")?,
+            on_hold: row.get("on_hold")?,
+            last_staff_reply_at: row.get("last_staff_reply_at")?,
+            awaiting_staff_since: row.get("awaiting_staff_since")?,
+        })
+    }
+
+    /// The channel name / human readable identifier of the ticket, e.g. `ticket-4`
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.topic_settings().channel_prefix, self.number)
+    }
+
+    /// The configured topic of this ticket, falling back to the default topic if it has since been
+    /// removed from the config.
+    pub fn topic_settings(&self) -> &'static TicketTopic {
+        let guild_settings = &BotConfig::global_cfg().guild_settings;
+        guild_settings
+            .topic(&self.topic)
+            .unwrap_or_else(|| guild_settings.default_topic())
+    }
+}
+
+/// A ban from opening tickets.
+#[derive(Debug, Clone)]
+pub struct TicketBan {
+    pub(crate) reason: Option<String>,
+    /// When the ban ends, or `None` if it is permanent
+    pub(crate) expires_at: Option<DateTime<Utc>>,
+}
+
+/// A ticket author's rating of how their ticket was handled.
+#[derive(Debug, Clone)]
+pub struct TicketRating {
+    pub(crate) ticket_id: i64,
+    /// From 1 to 5
+    pub(crate) score: u8,
+    pub(crate) feedback: Option<String>,
+}
+
+/// An internal note left on a ticket by staff, never shown to the ticket author.
+#[derive(Debug, Clone)]
+pub struct TicketNote {
+    pub(crate) author_id: UserId,
+    /// The author's name when they wrote the note, for transcripts
+    pub(crate) author_name: String,
+    pub(crate) body: String,
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+/// A closed ticket found by searching transcripts.
+#[derive(Debug, Clone)]
+pub struct TranscriptMatch {
+    pub(crate) ticket: Ticket,
+    /// The message in the ticket log channel the transcript was posted with
+    pub(crate) log_message: Option<(ChannelId, MessageId)>,
+    /// The part of the transcript matching the keywords, with matches in bold
+    pub(crate) snippet: Option<String>,
+}
+
+/// Persistent record of every ticket, backed by an sqlite database at `database_path`.
+pub struct TicketStore {
+    conn: Mutex<Connection>,
+}
+
+impl TicketStore {
+    pub fn global() -> &'static Self {
+        static INSTANCE: OnceCell<TicketStore> = OnceCell::new();
+        INSTANCE.get_or_init(|| {
+            let path = Path::new(&BotConfig::global_cfg().database_path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).expect("Error creating database directory");
+            }
+            let mut conn = Connection::open(path).expect("Error opening ticket database");
+            migrate(&mut conn).expect("Error migrating ticket database");
+            TicketStore {
+                conn: Mutex::new(conn),
+            }
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("ticket store lock poisoned")
+    }
+
+    /// Atomically reserves the next ticket number for `guild_id`. Numbers are never reused, even
+    /// if the ticket channel is never created.
+    pub fn next_ticket_number(&self, guild_id: GuildId) -> rusqlite::Result<u64> {
+        self.conn().query_row(
+            "INSERT INTO ticket_counters (guild_id, last_number) VALUES (?1, 1)
+             ON CONFLICT (guild_id) DO UPDATE SET last_number = last_number + 1
+             RETURNING last_number",
+            params![guild_id.get()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Whether `guild_id` has reserved a ticket number yet.
+    pub fn has_ticket_counter(&self, guild_id: GuildId) -> rusqlite::Result<bool> {
+        self.conn().query_row(
+            "SELECT EXISTS (SELECT 1 FROM ticket_counters WHERE guild_id = ?1)",
+            params![guild_id.get()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Makes sure the next ticket number reserved for `guild_id` is above `last_number`, e.g. the
+    /// highest number of the ticket channels created before tickets were stored.
+    pub fn seed_ticket_counter(&self, guild_id: GuildId, last_number: u64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO ticket_counters (guild_id, last_number) VALUES (?1, ?2)
+             ON CONFLICT (guild_id) DO UPDATE SET last_number = MAX(last_number, ?2)",
+            params![guild_id.get(), last_number],
+        )?;
+        Ok(())
+    }
+
+    pub fn open_ticket(
+        &self,
+        guild_id: GuildId,
+        number: u64,
+        topic: &str,
+        channel_id: ChannelId,
+        thread: bool,
+        opener_id: UserId,
+    ) -> rusqlite::Result<Ticket> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO tickets (guild_id, number, topic, channel_id, thread, opener_id,
+                 opened_at, last_activity_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+            params![
+                guild_id.get(),
+                number,
+                topic,
+                channel_id.get(),
+                thread,
+                opener_id.get(),
+                Utc::now()
+            ],
+        )?;
+        let ticket_id = tx.last_insert_rowid();
+        insert_event(&tx, ticket_id, EventKind::Opened, opener_id, None)?;
+        tx.commit()?;
+        get_ticket(&conn, ticket_id)
+    }
+
+    pub fn set_modmail(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET modmail = 1 WHERE id = ?1",
+            params![ticket_id],
+        )?;
+        Ok(())
+    }
+
+    /// Finds the open modmail ticket of `opener_id`, if any.
+    pub fn open_modmail_ticket(
+        &self,
+        guild_id: GuildId,
+        opener_id: UserId,
+    ) -> rusqlite::Result<Option<Ticket>> {
+        self.conn()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tickets
+                     WHERE guild_id = ?1 AND opener_id = ?2 AND modmail AND closed_at IS NULL
+                     ORDER BY opened_at DESC",
+                    Ticket::COLUMNS
+                ),
+                params![guild_id.get(), opener_id.get()],
+                Ticket::from_row,
+            )
+            .optional()
+    }
+
+    pub fn ticket(&self, ticket_id: i64) -> rusqlite::Result<Option<Ticket>> {
+        get_ticket(&self.conn(), ticket_id).optional()
+    }
+
+    /// Finds the open ticket whose channel is `channel_id`, if any.
+    pub fn open_ticket_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> rusqlite::Result<Option<Ticket>> {
+        self.conn()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tickets WHERE channel_id = ?1 AND closed_at IS NULL",
+                    Ticket::COLUMNS
+                ),
+                params![channel_id.get()],
+                Ticket::from_row,
+            )
+            .optional()
+    }
+
+    /// Records the opener's answers to the intake form, as `(question, answer)` pairs.
+    pub fn record_answers(
+        &self,
+        ticket_id: i64,
+        answers: &[(String, String)],
+    ) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for (position, (question, answer)) in answers.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO ticket_answers (ticket_id, position, question, answer)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![ticket_id, position, question, answer],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// The opener's answers to the intake form, as `(question, answer)` pairs.
+    pub fn answers(&self, ticket_id: i64) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT question, answer FROM ticket_answers WHERE ticket_id = ?1 ORDER BY position",
+        )?;
+        let answers = statement
+            .query_map(params![ticket_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        answers
+    }
+
+    pub fn add_note(
+        &self,
+        ticket_id: i64,
+        author_id: UserId,
+        author_name: &str,
+        body: &str,
+    ) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO ticket_notes (ticket_id, author_id, author_name, body, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![ticket_id, author_id.get(), author_name, body, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// The staff notes on a ticket, oldest first.
+    pub fn notes(&self, ticket_id: i64) -> rusqlite::Result<Vec<TicketNote>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT author_id, author_name, body, created_at FROM ticket_notes
+             WHERE ticket_id = ?1 ORDER BY id",
+        )?;
+        let notes = statement
+            .query_map(params![ticket_id], |row| {
+                Ok(TicketNote {
+                    author_id: UserId::new(row.get(0)?),
+                    author_name: row.get(1)?,
+                    body: row.get(2)?,
+                    created_at: row.get(3)?,
+                })
+            })?
+            .collect();
+        notes
+    }
+
+    pub fn set_welcome_message(
+        &self,
+        ticket_id: i64,
+        message_id: MessageId,
+    ) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET welcome_message_id = ?2 WHERE id = ?1",
+            params![ticket_id, message_id.get()],
+        )?;
+        Ok(())
+    }
+
+    /// Assigns the ticket to `claimer_id`, or unassigns it if `None`. `actor_id` is the staff
+    /// member making the change.
+    pub fn set_claimer(
+        &self,
+        ticket_id: i64,
+        claimer_id: Option<UserId>,
+        actor_id: UserId,
+    ) -> rusqlite::Result<Ticket> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET claimer_id = ?2 WHERE id = ?1",
+            params![ticket_id, claimer_id.map(UserId::get)],
+        )?;
+        let kind = match claimer_id {
+            Some(_) => EventKind::Claimed,
+            None => EventKind::Unclaimed,
+        };
+        insert_event(&tx, ticket_id, kind, actor_id, None)?;
+        tx.commit()?;
+        get_ticket(&conn, ticket_id)
+    }
+
+    /// Exempts the ticket from being closed for inactivity, or lifts the exemption.
+    pub fn set_on_hold(
+        &self,
+        ticket_id: i64,
+        on_hold: bool,
+        actor_id: UserId,
+    ) -> rusqlite::Result<Ticket> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET on_hold = ?2, inactivity_warned_at = NULL WHERE id = ?1",
+            params![ticket_id, on_hold],
+        )?;
+        let kind = if on_hold {
+            EventKind::Held
+        } else {
+            EventKind::Unheld
+        };
+        insert_event(&tx, ticket_id, kind, actor_id, None)?;
+        tx.commit()?;
+        get_ticket(&conn, ticket_id)
+    }
+
+    /// Records that someone wrote in the ticket, resetting its inactivity timer.
+    pub fn record_activity(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET last_activity_at = ?2, inactivity_warned_at = NULL WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Records that the ticket was warned it will be closed for inactivity.
+    pub fn record_inactivity_warning(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET inactivity_warned_at = ?2 WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Open tickets not on hold with no activity since `idle_since`, least recently active first.
+    pub fn idle_tickets(
+        &self,
+        guild_id: GuildId,
+        idle_since: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND closed_at IS NULL AND NOT on_hold AND last_activity_at < ?2
+             ORDER BY last_activity_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(params![guild_id.get(), idle_since], Ticket::from_row)?
+            .collect();
+        tickets
+    }
+
+    /// The tickets `opener_id` currently has open, oldest first.
+    pub fn open_tickets_by_opener(
+        &self,
+        guild_id: GuildId,
+        opener_id: UserId,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND opener_id = ?2 AND closed_at IS NULL
+             ORDER BY opened_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(params![guild_id.get(), opener_id.get()], Ticket::from_row)?
+            .collect();
+        tickets
+    }
+
+    /// When `opener_id` last opened a ticket, if ever.
+    pub fn last_opened_at(
+        &self,
+        guild_id: GuildId,
+        opener_id: UserId,
+    ) -> rusqlite::Result<Option<DateTime<Utc>>> {
+        self.conn().query_row(
+            "SELECT MAX(opened_at) FROM tickets WHERE guild_id = ?1 AND opener_id = ?2",
+            params![guild_id.get(), opener_id.get()],
+            |row| row.get(0),
+        )
+    }
+
+    /// Records a reply from a support team member, which also ends any SLA breach.
+    pub fn record_staff_reply(&self, ticket_id: i64, staff_id: UserId) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET first_staff_reply_at = COALESCE(first_staff_reply_at, ?2),
+                 last_staff_reply_at = ?2, awaiting_staff_since = NULL, sla_alerted_at = NULL
+             WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        insert_event(&tx, ticket_id, EventKind::StaffReplied, staff_id, None)?;
+        tx.commit()
+    }
+
+    /// Records a message from the ticket author or another non-staff member, which starts the
+    /// wait for a staff reply unless one has already started.
+    pub fn record_author_message(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET awaiting_staff_since = COALESCE(awaiting_staff_since, ?2)
+             WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Records that staff were alerted the ticket is waiting too long for a reply.
+    pub fn record_sla_alert(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET sla_alerted_at = ?2 WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Open tickets staff have not yet been alerted about that are waiting too long for a staff
+    /// reply: either no staff member has replied and it was opened before `opened_before`, or the
+    /// author wrote again after a staff reply and their first unanswered message was sent before
+    /// `wrote_before`.
+    pub fn unalerted_waiting_tickets(
+        &self,
+        guild_id: GuildId,
+        opened_before: DateTime<Utc>,
+        wrote_before: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND closed_at IS NULL AND sla_alerted_at IS NULL
+                 AND ((last_staff_reply_at IS NULL AND opened_at < ?2)
+                     OR (last_staff_reply_at IS NOT NULL AND awaiting_staff_since < ?3))
+             ORDER BY opened_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(
+                params![guild_id.get(), opened_before, wrote_before],
+                Ticket::from_row,
+            )?
+            .collect();
+        tickets
+    }
+
+    pub fn record_event(
+        &self,
+        ticket_id: i64,
+        kind: EventKind,
+        actor_id: UserId,
+        detail: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        insert_event(&self.conn(), ticket_id, kind, actor_id, detail)
+    }
+
+    /// The detail of the most recent event of `kind` on a ticket, if there is one with a detail.
+    pub fn latest_event_detail(
+        &self,
+        ticket_id: i64,
+        kind: EventKind,
+    ) -> rusqlite::Result<Option<String>> {
+        self.conn()
+            .query_row(
+                "SELECT detail FROM ticket_events WHERE ticket_id = ?1 AND kind = ?2
+                 ORDER BY id DESC LIMIT 1",
+                params![ticket_id, kind.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten)
+    }
+
+    /// Records the ticket as closed, or returns `None` if it already is, e.g. because two closes
+    /// raced.
+    pub fn close_ticket(
+        &self,
+        ticket_id: i64,
+        closer_id: UserId,
+        reason: Option<&str>,
+    ) -> rusqlite::Result<Option<Ticket>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let closed = tx.execute(
+            "UPDATE tickets SET closer_id = ?2, close_reason = ?3, closed_at = ?4
+             WHERE id = ?1 AND closed_at IS NULL",
+            params![ticket_id, closer_id.get(), reason, Utc::now()],
+        )?;
+        if closed == 0 {
+            return Ok(None);
+        }
+        insert_event(&tx, ticket_id, EventKind::Closed, closer_id, reason)?;
+        tx.commit()?;
+        get_ticket(&conn, ticket_id).map(Some)
+    }
+
+    /// Reverts a close that could not be completed, so the ticket is open again as if it had
+    /// never been closed. Its inactivity timer restarts, so the inactivity watch does not retry
+    /// closing it every minute.
+    pub fn undo_close(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET closer_id = NULL, close_reason = NULL, closed_at = NULL,
+                 last_activity_at = ?2, inactivity_warned_at = NULL
+             WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        tx.execute(
+            "DELETE FROM ticket_events WHERE id = (
+                 SELECT MAX(id) FROM ticket_events WHERE ticket_id = ?1 AND kind = ?2
+             )",
+            params![ticket_id, EventKind::Closed.as_str()],
+        )?;
+        tx.commit()
+    }
+
+    /// Tickets opened in `[from, to)`, oldest first.
+    pub fn tickets_opened_between(
+        &self,
+        guild_id: GuildId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND opened_at >= ?2 AND opened_at < ?3
+             ORDER BY opened_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(params![guild_id.get(), from, to], Ticket::from_row)?
+            .collect();
+        tickets
+    }
+
+    /// Tickets closed in `[from, to)`, oldest first.
+    pub fn tickets_closed_between(
+        &self,
+        guild_id: GuildId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND closed_at >= ?2 AND closed_at < ?3
+             ORDER BY closed_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(params![guild_id.get(), from, to], Ticket::from_row)?
+            .collect();
+        tickets
+    }
+
+    /// How many times each user has done `kind` to a ticket in `[from, to)`, most first. Events
+    /// by the ticket's own author are left out, so only staff are counted.
+    pub fn event_counts_by_actor(
+        &self,
+        guild_id: GuildId,
+        kind: EventKind,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<(UserId, u64)>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT e.actor_id, COUNT(*) AS count FROM ticket_events e
+             JOIN tickets t ON t.id = e.ticket_id
+             WHERE t.guild_id = ?1 AND e.kind = ?2 AND e.at >= ?3 AND e.at < ?4
+                 AND e.actor_id != t.opener_id
+             GROUP BY e.actor_id
+             ORDER BY count DESC",
+        )?;
+        let counts = statement
+            .query_map(params![guild_id.get(), kind.as_str(), from, to], |row| {
+                Ok((UserId::new(row.get(0)?), row.get(1)?))
+            })?
+            .collect();
+        counts
+    }
+
+    /// Records the author's rating of a ticket, replacing any earlier rating.
+    pub fn rate_ticket(&self, ticket_id: i64, score: u8) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO ticket_ratings (ticket_id, score, rated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (ticket_id) DO UPDATE SET score = ?2, rated_at = ?3",
+            params![ticket_id, score, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_rating_feedback(&self, ticket_id: i64, feedback: &str) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE ticket_ratings SET feedback = ?2 WHERE ticket_id = ?1",
+            params![ticket_id, feedback],
+        )?;
+        Ok(())
+    }
+
+    /// Ratings given since `since`, most recent first.
+    pub fn ratings_since(
+        &self,
+        guild_id: GuildId,
+        since: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<TicketRating>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT r.ticket_id, r.score, r.feedback FROM ticket_ratings r
+             JOIN tickets t ON t.id = r.ticket_id
+             WHERE t.guild_id = ?1 AND r.rated_at >= ?2
+             ORDER BY r.rated_at DESC",
+        )?;
+        let ratings = statement
+            .query_map(params![guild_id.get(), since], |row| {
+                Ok(TicketRating {
+                    ticket_id: row.get(0)?,
+                    score: row.get(1)?,
+                    feedback: row.get(2)?,
+                })
+            })?
+            .collect();
+        ratings
+    }
+
+    /// The number of rated tickets each staff member replied to since `since` and their average
+    /// score, best first.
+    pub fn staff_ratings_since(
+        &self,
+        guild_id: GuildId,
+        since: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<(UserId, u64, f64)>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT e.actor_id, COUNT(*), AVG(r.score) AS average FROM ticket_ratings r
+             JOIN tickets t ON t.id = r.ticket_id
+             JOIN (SELECT DISTINCT ticket_id, actor_id FROM ticket_events WHERE kind = ?3) e
+                 ON e.ticket_id = r.ticket_id
+             WHERE t.guild_id = ?1 AND r.rated_at >= ?2
+             GROUP BY e.actor_id
+             ORDER BY average DESC",
+        )?;
+        let ratings = statement
+            .query_map(
+                params![guild_id.get(), since, EventKind::StaffReplied.as_str()],
+                |row| Ok((UserId::new(row.get(0)?), row.get(1)?, row.get(2)?)),
+            )?
+            .collect();
+        ratings
+    }
+
+    /// Reopens a closed ticket whose channel was archived.
+    pub fn reopen_ticket(&self, ticket_id: i64, actor_id: UserId) -> rusqlite::Result<Ticket> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET closer_id = NULL, close_reason = NULL, closed_at = NULL,
+                 last_activity_at = ?2, inactivity_warned_at = NULL, sla_alerted_at = NULL,
+                 awaiting_staff_since = NULL
+             WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        insert_event(&tx, ticket_id, EventKind::Reopened, actor_id, None)?;
+        tx.commit()?;
+        get_ticket(&conn, ticket_id)
+    }
+
+    /// Finds the closed ticket whose channel `channel_id` is kept as an archive, if any.
+    pub fn archived_ticket_by_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> rusqlite::Result<Option<Ticket>> {
+        self.conn()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tickets
+                     WHERE channel_id = ?1 AND closed_at IS NOT NULL
+                         AND channel_deleted_at IS NULL
+                     ORDER BY closed_at DESC",
+                    Ticket::COLUMNS
+                ),
+                params![channel_id.get()],
+                Ticket::from_row,
+            )
+            .optional()
+    }
+
+    /// Closed tickets whose channels are still kept as archives but were closed before
+    /// `closed_before`.
+    pub fn expired_archives(
+        &self,
+        guild_id: GuildId,
+        closed_before: DateTime<Utc>,
+    ) -> rusqlite::Result<Vec<Ticket>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT {} FROM tickets
+             WHERE guild_id = ?1 AND closed_at < ?2 AND channel_deleted_at IS NULL
+             ORDER BY closed_at",
+            Ticket::COLUMNS
+        ))?;
+        let tickets = statement
+            .query_map(params![guild_id.get(), closed_before], Ticket::from_row)?
+            .collect();
+        tickets
+    }
+
+    /// Records that the ticket's channel has been deleted.
+    pub fn set_channel_deleted(&self, ticket_id: i64) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE tickets SET channel_deleted_at = ?2 WHERE id = ?1",
+            params![ticket_id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Indexes the text transcript of a closed ticket for searching, replacing any from an earlier
+    /// close, and records the log message it was posted with.
+    pub fn index_transcript(
+        &self,
+        ticket_id: i64,
+        log_channel_id: ChannelId,
+        log_message_id: MessageId,
+        transcript: &str,
+    ) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE tickets SET log_channel_id = ?2, log_message_id = ?3 WHERE id = ?1",
+            params![ticket_id, log_channel_id.get(), log_message_id.get()],
+        )?;
+        tx.execute(
+            "DELETE FROM ticket_transcripts WHERE rowid = ?1",
+            params![ticket_id],
+        )?;
+        tx.execute(
+            "INSERT INTO ticket_transcripts (rowid, transcript) VALUES (?1, ?2)",
+            params![ticket_id, transcript],
+        )?;
+        tx.commit()
+    }
+
+    /// Closed tickets, most recently closed first, opened by `opener_id` and closed in
+    /// `[from, to)` if given, whose transcripts contain every word in `keywords`.
+    pub fn search_transcripts(
+        &self,
+        guild_id: GuildId,
+        opener_id: Option<UserId>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        keywords: Option<&str>,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<TranscriptMatch>> {
+        let filters = "guild_id = ?1 AND closed_at IS NOT NULL
+             AND (?2 IS NULL OR opener_id = ?2)
+             AND (?3 IS NULL OR closed_at >= ?3)
+             AND (?4 IS NULL OR closed_at < ?4)";
+        let query = keywords.map(fts_query).filter(|query| !query.is_empty());
+        let sql = match query {
+            Some(_) => format!(
+                "SELECT {}, log_channel_id, log_message_id,
+                     snippet(ticket_transcripts, 0, '**', '**', '…', 16) AS snippet
+                 FROM tickets JOIN ticket_transcripts ON ticket_transcripts.rowid = tickets.id
+                 WHERE {} AND ticket_transcripts MATCH ?6
+                 ORDER BY closed_at DESC LIMIT ?5",
+                Ticket::COLUMNS,
+                filters
+            ),
+            None => format!(
+                "SELECT {}, log_channel_id, log_message_id, NULL AS snippet
+                 FROM tickets
+                 WHERE {}
+                 ORDER BY closed_at DESC LIMIT ?5",
+                Ticket::COLUMNS,
+                filters
+            ),
+        };
+
+        let guild_id = guild_id.get();
+        let opener_id = opener_id.map(|id| id.get());
+        let limit = limit as i64;
+        let mut values = params![guild_id, opener_id, from, to, limit].to_vec();
+        if let Some(query) = &query {
+            values.push(query);
+        }
+
+        let conn = self.conn();
+        let mut statement = conn.prepare(&sql)?;
+        let matches = statement
+            .query_map(values.as_slice(), |row| {
+                let log_channel_id = row.get::<_, Option<u64>>("log_channel_id")?;
+                let log_message_id = row.get::<_, Option<u64>>("log_message_id")?;
+                let log_message = match (log_channel_id, log_message_id) {
+                    (Some(channel), Some(message)) => {
+                        Some((ChannelId::new(channel), MessageId::new(message)))
+                    }
+                    _ => None,
+                };
+                Ok(TranscriptMatch {
+                    ticket: Ticket::from_row(row)?,
+                    log_message,
+                    snippet: row.get("snippet")?,
+                })
+            })?
+            .collect();
+        matches
+    }
+
+    /// Saves a new canned response, returning `false` if one called `name` already exists.
+    pub fn add_snippet(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        body: &str,
+        author_id: UserId,
+    ) -> rusqlite::Result<bool> {
+        let added = self.conn().execute(
+            "INSERT OR IGNORE INTO ticket_snippets (guild_id, name, body, updated_by, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![guild_id.get(), name, body, author_id.get(), Utc::now()],
+        )?;
+        Ok(added > 0)
+    }
+
+    /// Replaces the text of a canned response, returning `false` if there is none called `name`.
+    pub fn edit_snippet(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+        body: &str,
+        author_id: UserId,
+    ) -> rusqlite::Result<bool> {
+        let edited = self.conn().execute(
+            "UPDATE ticket_snippets SET body = ?3, updated_by = ?4, updated_at = ?5
+             WHERE guild_id = ?1 AND name = ?2",
+            params![guild_id.get(), name, body, author_id.get(), Utc::now()],
+        )?;
+        Ok(edited > 0)
+    }
+
+    /// Deletes a canned response, returning whether it existed.
+    pub fn remove_snippet(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM ticket_snippets WHERE guild_id = ?1 AND name = ?2",
+            params![guild_id.get(), name],
+        )?;
+        Ok(removed > 0)
+    }
+
+    pub fn snippet(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<Option<String>> {
+        self.conn()
+            .query_row(
+                "SELECT body FROM ticket_snippets WHERE guild_id = ?1 AND name = ?2",
+                params![guild_id.get(), name],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// The names of all canned responses, alphabetically.
+    pub fn snippet_names(&self, guild_id: GuildId) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn();
+        let mut statement =
+            conn.prepare("SELECT name FROM ticket_snippets WHERE guild_id = ?1 ORDER BY name")?;
+        let names = statement
+            .query_map(params![guild_id.get()], |row| row.get(0))?
+            .collect();
+        names
+    }
+
+    /// Bans `user_id` from opening tickets until `expires_at`, or permanently if `None`. Replaces
+    /// any existing ban.
+    pub fn ban_user(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        banned_by: UserId,
+        reason: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO ticket_bans
+                 (guild_id, user_id, banned_by, reason, banned_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                guild_id.get(),
+                user_id.get(),
+                banned_by.get(),
+                reason,
+                Utc::now(),
+                expires_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Lifts a ban, returning whether the user was banned.
+    pub fn unban_user(&self, guild_id: GuildId, user_id: UserId) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM ticket_bans WHERE guild_id = ?1 AND user_id = ?2",
+            params![guild_id.get(), user_id.get()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// The ban currently preventing `user_id` from opening tickets, if any.
+    pub fn active_ban(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> rusqlite::Result<Option<TicketBan>> {
+        let ban = self
+            .conn()
+            .query_row(
+                "SELECT reason, expires_at FROM ticket_bans WHERE guild_id = ?1 AND user_id = ?2",
+                params![guild_id.get(), user_id.get()],
+                |row| {
+                    Ok(TicketBan {
+                        reason: row.get("reason")?,
+                        expires_at: row.get("expires_at")?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(ban.filter(|ban| {
+            ban.expires_at
+                .is_none_or(|expires_at| expires_at > Utc::now())
+        }))
+    }
+}
+
+/// Quotes each word of a search so that full-text query syntax in it is matched literally.
+fn fts_query(keywords: &str) -> String {
+    keywords
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn insert_event(
+    conn: &Connection,
+    ticket_id: i64,
+    kind: EventKind,
+    actor_id: UserId,
+    detail: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO ticket_events (ticket_id, kind, actor_id, detail, at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![ticket_id, kind.as_str(), actor_id.get(), detail, Utc::now()],
+    )?;
+    Ok(())
+}
+
+fn get_ticket(conn: &Connection, ticket_id: i64) -> rusqlite::Result<Ticket> {
+    conn.query_row(
+        &format!("SELECT {} FROM tickets WHERE id = ?1", Ticket::COLUMNS),
+        params![ticket_id],
+        Ticket::from_row,
+    )
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Applying ticket database migration {}", i + 1);
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::{TimeDelta, Utc};
+    use rusqlite::{params, Connection};
+    use serenity::model::id::{ChannelId, GuildId, UserId};
+
+    use super::{migrate, EventKind, Ticket, TicketStore, MIGRATIONS};
+
+    const GUILD: GuildId = GuildId::new(1);
+    const OPENER: UserId = UserId::new(2);
+    const STAFF: UserId = UserId::new(3);
+
+    fn store() -> TicketStore {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        TicketStore {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    fn open(store: &TicketStore) -> Ticket {
+        store
+            .open_ticket(GUILD, 1, "support", ChannelId::new(4), false, OPENER)
+            .unwrap()
+    }
+
+    fn closed_events(store: &TicketStore, ticket_id: i64) -> u32 {
+        store
+            .conn()
+            .query_row(
+                "SELECT COUNT(*) FROM ticket_events WHERE ticket_id = ?1 AND kind = ?2",
+                params![ticket_id, EventKind::Closed.as_str()],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn migrations_apply_from_version_zero() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn follow_up_waits_for_the_sla_after_a_staff_reply() {
+        let store = store();
+        let ticket = open(&store);
+        let long_ago = Utc::now() - TimeDelta::days(1);
+        let soon = Utc::now() + TimeDelta::minutes(1);
+
+        store.record_staff_reply(ticket.id, STAFF).unwrap();
+        assert!(store
+            .unalerted_waiting_tickets(GUILD, long_ago, soon)
+            .unwrap()
+            .is_empty());
+
+        store.record_author_message(ticket.id).unwrap();
+        assert!(store
+            .unalerted_waiting_tickets(GUILD, long_ago, long_ago)
+            .unwrap()
+            .is_empty());
+        let waiting = store
+            .unalerted_waiting_tickets(GUILD, long_ago, soon)
+            .unwrap();
+        assert_eq!(waiting.len(), 1);
+        assert_eq!(waiting[0].id, ticket.id);
+
+        store.record_staff_reply(ticket.id, STAFF).unwrap();
+        assert!(store
+            .unalerted_waiting_tickets(GUILD, long_ago, soon)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn closing_twice_returns_none() {
+        let store = store();
+        let ticket = open(&store);
+        assert!(store
+            .close_ticket(ticket.id, STAFF, None)
+            .unwrap()
+            .is_some());
+        assert!(store
+            .close_ticket(ticket.id, STAFF, None)
+            .unwrap()
+            .is_none());
+        assert_eq!(closed_events(&store, ticket.id), 1);
+    }
+
+    #[test]
+    fn undo_close_removes_the_closed_event() {
+        let store = store();
+        let ticket = open(&store);
+        store
+            .close_ticket(ticket.id, STAFF, Some("done"))
+            .unwrap()
+            .unwrap();
+        store.undo_close(ticket.id).unwrap();
+
+        let ticket = store.ticket(ticket.id).unwrap().unwrap();
+        assert!(ticket.closed_at.is_none());
+        assert_eq!(closed_events(&store, ticket.id), 0);
+        assert!(store
+            .open_ticket_by_channel(ticket.channel_id)
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt::Write;
+
+use chrono::DateTime;
+use serenity::all::{Embed, Message, ReactionType, Timestamp};
+
+use crate::tickets::store::TicketNote;
+
+const STYLE: &str = "body { background: #313338; color: #dbdee1; font-family: sans-serif; margin: 0; padding: 16px; }
+h1 { font-size: 20px; border-bottom: 1px solid #4e5058; padding-bottom: 8px; }
+.message { display: flex; padding: 6px 0; }
+.avatar { width: 40px; height: 40px; border-radius: 50%; margin-right: 12px; flex-shrink: 0; }
+.name { font-weight: bold; color: #f2f3f5; }
+.bot { background: #5865f2; color: #fff; font-size: 10px; border-radius: 3px; padding: 1px 4px; margin-left: 4px; }
+.timestamp, .edited { color: #949ba4; font-size: 12px; margin-left: 6px; }
+.reply { color: #b5bac1; font-size: 13px; margin-bottom: 2px; }
+.reply a { color: #b5bac1; }
+.content { white-space: pre-wrap; word-wrap: break-word; }
+.embed { background: #2b2d31; border-left: 4px solid #1e1f22; border-radius: 4px; padding: 8px 12px; margin-top: 4px; max-width: 520px; }
+.embed-title { font-weight: bold; color: #f2f3f5; }
+.embed-fields { display: flex; flex-wrap: wrap; }
+.embed-field { margin-top: 6px; min-width: 100%; }
+.embed-field.inline { min-width: 150px; flex: 1; }
+.embed-field-name { font-weight: bold; font-size: 13px; }
+.embed-footer { color: #949ba4; font-size: 12px; margin-top: 6px; }
+.embed-thumbnail { float: right; max-width: 80px; max-height: 80px; margin-left: 8px; }
+.attachment img, .embed-image { max-width: 400px; max-height: 300px; margin-top: 4px; border-radius: 4px; }
+.attachment a { color: #00a8fc; }
+.reactions { margin-top: 4px; }
+.reaction { background: #2b2d31; border-radius: 6px; padding: 2px 6px; margin-right: 4px; font-size: 13px; }
+.reaction img { width: 16px; height: 16px; vertical-align: middle; }
+.note { background: #3f3a26; border-left: 4px solid #f0b232; border-radius: 4px; padding: 6px 12px; margin: 6px 0; }
+.note-label { color: #f0b232; font-size: 12px; font-weight: bold; text-transform: uppercase; }";
+
+/// Renders a plain text transcript, one `[timestamp] name: content` line per message, followed by
+/// the text of its embeds such as relayed modmail. Messages should be given oldest first.
+pub fn render_text(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let mut line = format!(
+                "[{}] {}: {}",
+                message.timestamp, message.author.name, message.content
+            );
+            for embed in &message.embeds {
+                let author = embed.author.as_ref().map(|author| author.name.as_str());
+                if let (Some(author), Some(description)) = (author, &embed.description) {
+                    let _ = write!(line, "\n    {}: {}", author, description);
+                }
+            }
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Renders a self-contained HTML transcript styled after the Discord client, including avatars,
+/// replies, embeds, attachments and reactions. Messages should be given oldest first.
+pub fn render_html(title: &str, messages: &[Message]) -> String {
+    render(title, messages, &[])
+}
+
+/// Renders the HTML transcript with the staff `notes` shown between the messages they were
+/// written after, for staff only. Both should be given oldest first.
+pub fn render_staff_html(title: &str, messages: &[Message], notes: &[TicketNote]) -> String {
+    render(title, messages, notes)
+}
+
+fn render(title: &str, messages: &[Message], notes: &[TicketNote]) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(title),
+        STYLE,
+        escape(title)
+    );
+    let mut notes = notes.iter().peekable();
+    for message in messages {
+        while let Some(note) =
+            notes.next_if(|note| note.created_at.timestamp() < message.timestamp.unix_timestamp())
+        {
+            render_note(&mut html, note);
+        }
+        render_message(&mut html, message);
+    }
+    for note in notes {
+        render_note(&mut html, note);
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_message(html: &mut String, message: &Message) {
+    let _ = write!(
+        html,
+        "<div class=\"message\" id=\"m{}\">\n<img class=\"avatar\" src=\"{}\" alt=\"\">\n<div>\n",
+        message.id,
+        escape(&message.author.face())
+    );
+
+    if let Some(referenced) = &message.referenced_message {
+        let _ = writeln!(
+            html,
+            "<div class=\"reply\">&#8618; <a href=\"#m{}\">{}</a>: {}</div>",
+            referenced.id,
+            escape(referenced.author.display_name()),
+            escape(&snippet(&referenced.content, 100))
+        );
+    }
+
+    let _ = write!(
+        html,
+        "<span class=\"name\" title=\"{}\">{}</span>",
+        escape(&message.author.name),
+        escape(message.author.display_name())
+    );
+    if message.author.bot {
+        html.push_str("<span class=\"bot\">BOT</span>");
+    }
+    let _ = write!(
+        html,
+        "<span class=\"timestamp\">{}</span>",
+        format_timestamp(message.timestamp)
+    );
+    if let Some(edited) = message.edited_timestamp {
+        let _ = write!(
+            html,
+            "<span class=\"edited\" title=\"{}\">(edited)</span>",
+            format_timestamp(edited)
+        );
+    }
+
+    if !message.content.is_empty() {
+        let _ = write!(
+            html,
+            "\n<div class=\"content\">{}</div>",
+            escape(&resolve_mentions(message))
+        );
+    }
+
+    for embed in &message.embeds {
+        render_embed(html, embed);
+    }
+
+    for attachment in &message.attachments {
+        let is_image = attachment
+            .content_type
+            .as_ref()
+            .is_some_and(|content_type| content_type.starts_with("image/"));
+        if is_image {
+            let _ = write!(
+                html,
+                "\n<div class=\"attachment\"><a href=\"{0}\"><img src=\"{0}\" alt=\"{1}\"></a></div>",
+                escape(&attachment.url),
+                escape(&attachment.filename)
+            );
+        } else {
+            let _ = write!(
+                html,
+                "\n<div class=\"attachment\">&#128206; <a href=\"{}\">{}</a> ({} bytes)</div>",
+                escape(&attachment.url),
+                escape(&attachment.filename),
+                attachment.size
+            );
+        }
+    }
+
+    if !message.reactions.is_empty() {
+        html.push_str("\n<div class=\"reactions\">");
+        for reaction in &message.reactions {
+            let emoji = match &reaction.reaction_type {
+                ReactionType::Custom { animated, id, name } => format!(
+                    "<img src=\"https://cdn.discordapp.com/emojis/{}.{}\" alt=\"{}\">",
+                    id,
+                    if *animated { "gif" } else { "png" },
+                    escape(name.as_deref().unwrap_or_default())
+                ),
+                ReactionType::Unicode(unicode) => escape(unicode),
+                other => escape(&other.to_string()),
+            };
+            let _ = write!(
+                html,
+                "<span class=\"reaction\">{} {}</span>",
+                emoji, reaction.count
+            );
+        }
+        html.push_str("</div>");
+    }
+
+    html.push_str("\n</div>\n</div>\n");
+}
+
+fn render_note(html: &mut String, note: &TicketNote) {
+    let _ = write!(
+        html,
+        "<div class=\"note\">\n<div class=\"note-label\">Staff note</div>\n<span class=\"name\" title=\"{}\">{}</span><span class=\"timestamp\">{}</span>\n<div class=\"content\">{}</div>\n</div>\n",
+        note.author_id,
+        escape(&note.author_name),
+        note.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        escape(&note.body)
+    );
+}
+
+fn render_embed(html: &mut String, embed: &Embed) {
+    let border = embed
+        .colour
+        .map(|colour| format!(" style=\"border-left-color: #{}\"", colour.hex()))
+        .unwrap_or_default();
+    let _ = write!(html, "\n<div class=\"embed\"{}>", border);
+
+    if let Some(thumbnail) = &embed.thumbnail {
+        let _ = write!(
+            html,
+            "<img class=\"embed-thumbnail\" src=\"{}\" alt=\"\">",
+            escape(&thumbnail.url)
+        );
+    }
+    if let Some(author) = &embed.author {
+        let _ = write!(html, "<div class=\"name\">{}</div>", escape(&author.name));
+    }
+    if let Some(title) = &embed.title {
+        match &embed.url {
+            Some(url) => {
+                let _ = write!(
+                    html,
+                    "<div class=\"embed-title\"><a href=\"{}\">{}</a></div>",
+                    escape(url),
+                    escape(title)
+                );
+            }
+            None => {
+                let _ = write!(html, "<div class=\"embed-title\">{}</div>", escape(title));
+            }
+        }
+    }
+    if let Some(description) = &embed.description {
+        let _ = write!(html, "<div class=\"content\">{}</div>", escape(description));
+    }
+    if !embed.fields.is_empty() {
+        html.push_str("<div class=\"embed-fields\">");
+        for field in &embed.fields {
+            let _ = write!(
+                html,
+                "<div class=\"embed-field{}\"><div class=\"embed-field-name\">{}</div><div class=\"content\">{}</div></div>",
+                if field.inline { " inline" } else { "" },
+                escape(&field.name),
+                escape(&field.value)
+            );
+        }
+        html.push_str("</div>");
+    }
+    if let Some(image) = &embed.image {
+        let _ = write!(
+            html,
+            "<img class=\"embed-image\" src=\"{}\" alt=\"\">",
+            escape(&image.url)
+        );
+    }
+    if let Some(footer) = &embed.footer {
+        let _ = write!(
+            html,
+            "<div class=\"embed-footer\">{}</div>",
+            escape(&footer.text)
+        );
+    }
+    html.push_str("</div>");
+}
+
+/// Replaces raw `<@id>` user mentions with the mentioned user's display name.
+fn resolve_mentions(message: &Message) -> String {
+    let mut content = message.content.clone();
+    for user in &message.mentions {
+        let name = format!("@{}", user.display_name());
+        content = content
+            .replace(&format!("<@{}>", user.id), &name)
+            .replace(&format!("<@!{}>", user.id), &name);
+    }
+    content
+}
+
+fn format_timestamp(timestamp: Timestamp) -> String {
+    DateTime::from_timestamp(timestamp.unix_timestamp(), 0)
+        .map(|datetime| datetime.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_default()
+}
+
+fn snippet(content: &str, max_chars: usize) -> String {
+    if content.chars().count() <= max_chars {
+        content.to_string()
+    } else {
+        content.chars().take(max_chars).collect::<String>() + "…"
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}